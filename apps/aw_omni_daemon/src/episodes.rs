@@ -0,0 +1,130 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use chrono::{Duration, Utc};
+use nowframe_core::{segment, Episode, ListQuery, SegmenterConfig};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{new_redactor, nowframe_store, Config};
+
+#[derive(Debug, Deserialize)]
+pub struct EpisodesConfig {
+    /// Episodes are derived data about the user's work; nothing is computed or
+    /// stored until this is set (DATA_GOVERNANCE.md).
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_max_age_days")]
+    pub max_age_days: u64,
+    #[serde(flatten)]
+    pub segmenter: SegmenterConfig,
+}
+
+impl Default for EpisodesConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_age_days: default_max_age_days(),
+            segmenter: SegmenterConfig::default(),
+        }
+    }
+}
+
+fn default_max_age_days() -> u64 {
+    7
+}
+
+pub struct EpisodesOptions {
+    pub hours: i64,
+    pub save: bool,
+}
+
+pub fn run(cfg: &Config, options: &EpisodesOptions) -> Result<()> {
+    if !cfg.episodes.enabled {
+        bail!("episodes are disabled; set [episodes] enabled = true to opt in");
+    }
+    let store = nowframe_store(cfg);
+    let entries = store.list(&ListQuery {
+        since: Some(Utc::now() - Duration::hours(options.hours)),
+        ..ListQuery::default()
+    })?;
+    let redactor = new_redactor(cfg)?;
+    let mut frames = Vec::with_capacity(entries.len());
+    for entry in entries.iter().rev() {
+        match store.get(&entry.id) {
+            Ok(mut frame) => {
+                // Stored frames were redacted on write; re-apply in case the rules changed.
+                frame.redact(&redactor);
+                frames.push(frame);
+            }
+            Err(err) => eprintln!("episodes: skipping frame {}: {}", entry.id, err),
+        }
+    }
+    let episodes = segment(&frames, &cfg.episodes.segmenter);
+
+    let saved = if options.save {
+        let dir = PathBuf::from(&cfg.paths.data_episodes);
+        save_episodes(&dir, &episodes)?;
+        let pruned = prune_episodes(&dir, cfg.episodes.max_age_days)?;
+        Some(json!({ "dir": dir.to_string_lossy(), "pruned": pruned }))
+    } else {
+        None
+    };
+
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&json!({
+            "frames": frames.len(),
+            "episodes": episodes,
+            "saved": saved,
+        }))?
+    );
+    Ok(())
+}
+
+fn save_episodes(dir: &Path, episodes: &[Episode]) -> Result<()> {
+    fs::create_dir_all(dir)
+        .with_context(|| format!("create episode dir failed: {}", dir.display()))?;
+    for episode in episodes {
+        // Ids are derived from the start time, so re-running overwrites the same file.
+        let path = dir.join(format!("{}.json", episode.id));
+        fs::write(&path, serde_json::to_string_pretty(episode)?)
+            .with_context(|| format!("write episode failed: {}", path.display()))?;
+    }
+    Ok(())
+}
+
+fn prune_episodes(dir: &Path, max_age_days: u64) -> Result<usize> {
+    // An age too large to subtract from now keeps every episode.
+    let Some(cutoff) = i64::try_from(max_age_days)
+        .ok()
+        .and_then(Duration::try_days)
+        .and_then(|age| Utc::now().checked_sub_signed(age))
+    else {
+        return Ok(0);
+    };
+    let mut pruned = 0;
+    for item in fs::read_dir(dir)? {
+        let path = item?.path();
+        let ours = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(|n| n.starts_with("ep_") && n.ends_with(".json"));
+        if !ours {
+            continue;
+        }
+        let Ok(text) = fs::read_to_string(&path) else {
+            continue;
+        };
+        let Ok(episode) = serde_json::from_str::<Episode>(&text) else {
+            continue;
+        };
+        if episode.end < cutoff {
+            fs::remove_file(&path)
+                .with_context(|| format!("remove episode failed: {}", path.display()))?;
+            pruned += 1;
+        }
+    }
+    Ok(pruned)
+}
@@ -1,15 +1,25 @@
 use std::fs;
 use std::path::Path;
+use std::time::Duration;
 
 use anyhow::{Context, Result};
-use aw_client::AwClient;
+use aw_client::{AwClient, CurrentActivity, Event};
 use chrono::Utc;
 use clap::{Parser, Subcommand};
-use nowframe_core::NowFrame;
+use http_transport::TransportConfig;
+use nowframe_core::{
+    CacheManager, CachePolicy, ListQuery, NowFrame, NowFrameStore, RetentionPolicy, ScreenSummary,
+    MAX_EPISODE_HOURS,
+};
 use omni_client::OmniClient;
+use redaction::{RedactionConfig, Redactor};
 use serde::Deserialize;
-use serde_json::{json, Value};
-use trigger_engine::{score, TriggerInput};
+use serde_json::{json, Map, Value};
+use trigger_engine::Decision;
+
+mod episodes;
+mod sidecar;
+mod watch;
 
 #[derive(Parser, Debug)]
 #[command(name = "aw_omni_daemon")]
@@ -28,6 +38,85 @@ enum Command {
         #[arg(long)]
         reason: String,
     },
+    /// Run the Python sidecar from [sidecar] and restart it when it exits.
+    Sidecar {
+        /// Overrides [sidecar] sidecar_mode.
+        #[arg(long)]
+        mode: Option<String>,
+        #[arg(long, default_value_t = 120)]
+        ready_timeout_secs: u64,
+        /// Give up after this many restarts (default: restart forever).
+        #[arg(long)]
+        max_restarts: Option<u32>,
+    },
+    /// Poll AW and persist a NowFrame whenever the trigger score crosses the threshold.
+    Watch {
+        /// Overrides [watch] poll_interval_secs.
+        #[arg(long)]
+        interval_secs: Option<u64>,
+        /// Overrides [watch] threshold.
+        #[arg(long)]
+        threshold: Option<f64>,
+        /// Overrides [trigger] rules_path.
+        #[arg(long)]
+        rules: Option<String>,
+        /// Stop after this many polls (default: run forever).
+        #[arg(long)]
+        max_polls: Option<u64>,
+    },
+    /// Segment stored NowFrames into episodes (requires [episodes] enabled = true).
+    Episodes {
+        /// Look back this many hours.
+        #[arg(
+            long,
+            default_value_t = 24,
+            value_parser = clap::value_parser!(i64).range(1..=MAX_EPISODE_HOURS)
+        )]
+        hours: i64,
+        /// Also write the episodes to paths.data_episodes and prune old ones.
+        #[arg(long)]
+        save: bool,
+    },
+    /// Inspect or prune NowFrames stored under paths.data_nowframes.
+    Nowframes {
+        #[command(subcommand)]
+        action: NowframesAction,
+    },
+    /// Inspect or enforce the [cache] budget for paths.cache_screens/thumbs/tmp.
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum CacheAction {
+    /// Per-directory usage against the configured limits.
+    Stats,
+    /// Evict expired and least recently used frames until every directory fits.
+    Gc {
+        /// Report what would be removed without deleting anything.
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum NowframesAction {
+    /// List stored frames, newest first.
+    List {
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+        /// Only frames from the last N minutes.
+        #[arg(long)]
+        since_minutes: Option<i64>,
+    },
+    Get {
+        id: String,
+    },
+    Latest,
+    /// Apply [nowframes] retention now.
+    Prune,
 }
 
 #[derive(Debug, Deserialize)]
@@ -36,19 +125,91 @@ struct Config {
     omni: EndpointConfig,
     paths: PathsConfig,
     sidecar: Option<SidecarConfig>,
+    aw_heartbeat: Option<HeartbeatConfig>,
+    #[serde(default)]
+    watch: watch::WatchConfig,
+    #[serde(default)]
+    trigger: TriggerConfig,
+    #[serde(default)]
+    nowframes: RetentionPolicy,
+    #[serde(default)]
+    episodes: episodes::EpisodesConfig,
+    #[serde(default)]
+    redaction: RedactionConfig,
+    #[serde(default)]
+    cache: CachePolicy,
+}
+
+#[derive(Debug, Deserialize)]
+struct TriggerConfig {
+    /// TOML file with `[[rules]]`; the builtin rule set is used when unset.
+    rules_path: Option<String>,
+    /// Defaults to `<paths.root>/runtime/trigger_state.json`.
+    state_path: Option<String>,
+    #[serde(default = "default_rearm_below")]
+    rearm_below: f64,
+    #[serde(default = "default_rearm_after_secs")]
+    rearm_after_secs: u64,
+    #[serde(default)]
+    debounce_secs: u64,
+    #[serde(default)]
+    min_interval_secs: u64,
+    /// 0 disables the hourly budget.
+    #[serde(default)]
+    max_per_hour: u32,
+}
+
+impl Default for TriggerConfig {
+    fn default() -> Self {
+        Self {
+            rules_path: None,
+            state_path: None,
+            rearm_below: default_rearm_below(),
+            rearm_after_secs: default_rearm_after_secs(),
+            debounce_secs: 0,
+            min_interval_secs: 0,
+            max_per_hour: 0,
+        }
+    }
+}
+
+fn default_rearm_below() -> f64 {
+    0.3
+}
+
+fn default_rearm_after_secs() -> u64 {
+    30
 }
 
 #[derive(Debug, Deserialize)]
 struct EndpointConfig {
     base_url: String,
+    #[serde(flatten)]
+    transport: TransportConfig,
+}
+
+#[derive(Debug, Deserialize)]
+struct HeartbeatConfig {
+    #[serde(default)]
+    enabled: bool,
+    #[serde(default = "default_pulsetime_secs")]
+    pulsetime_secs: f64,
+}
+
+fn default_pulsetime_secs() -> f64 {
+    60.0
 }
 
 #[derive(Debug, Deserialize)]
 struct SidecarConfig {
     python: Option<String>,
     script: Option<String>,
+    sidecar_mode: Option<String>,
+    real_repo: Option<String>,
+    weights_root: Option<String>,
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Deserialize)]
 struct PathsConfig {
     root: String,
@@ -75,14 +236,95 @@ fn main() -> Result<()> {
             println!("{}", serde_json::to_string_pretty(&report)?);
         }
         Command::BuildNowframe { reason } => {
-            let nowframe = build_nowframe(&cfg, &reason);
+            let redactor = new_redactor(&cfg)?;
+            let nowframe = build_nowframe(&cfg, &reason, &redactor);
+            if let Err(err) = nowframe_store(&cfg).save(&nowframe) {
+                eprintln!("nowframe save failed: {}", err);
+            }
+            if let Err(err) = emit_nowframe_heartbeat(&cfg, &nowframe) {
+                eprintln!("aw heartbeat failed: {}", err);
+            }
             println!("{}", serde_json::to_string_pretty(&nowframe)?);
         }
+        Command::Sidecar {
+            mode,
+            ready_timeout_secs,
+            max_restarts,
+        } => {
+            sidecar::supervise(
+                &cfg,
+                &sidecar::SupervisorOptions {
+                    mode,
+                    ready_timeout: Duration::from_secs(ready_timeout_secs),
+                    max_restarts,
+                },
+            )?;
+        }
+        Command::Watch {
+            interval_secs,
+            threshold,
+            rules,
+            max_polls,
+        } => {
+            watch::run(
+                &cfg,
+                &watch::WatchOptions {
+                    poll_interval: Duration::from_secs(
+                        interval_secs.unwrap_or(cfg.watch.poll_interval_secs),
+                    ),
+                    threshold: threshold.unwrap_or(cfg.watch.threshold),
+                    rules_path: rules.or_else(|| cfg.trigger.rules_path.clone()),
+                    max_polls,
+                },
+            )?;
+        }
+        Command::Episodes { hours, save } => {
+            episodes::run(&cfg, &episodes::EpisodesOptions { hours, save })?;
+        }
+        Command::Nowframes { action } => {
+            let store = nowframe_store(&cfg);
+            let output = match action {
+                NowframesAction::List {
+                    limit,
+                    since_minutes,
+                } => {
+                    let entries = store.list(&ListQuery {
+                        since: since_minutes.map(|m| Utc::now() - chrono::Duration::minutes(m)),
+                        until: None,
+                        limit: Some(limit),
+                    })?;
+                    json!({ "frames": entries })
+                }
+                NowframesAction::Get { id } => serde_json::to_value(store.get(&id)?)?,
+                NowframesAction::Latest => match store.latest()? {
+                    Some((entry, frame)) => json!({ "id": entry.id, "frame": frame }),
+                    None => Value::Null,
+                },
+                NowframesAction::Prune => serde_json::to_value(store.prune(Utc::now())?)?,
+            };
+            println!("{}", serde_json::to_string_pretty(&output)?);
+        }
+        Command::Cache { action } => {
+            let cache = cache_manager(&cfg);
+            let output = match action {
+                CacheAction::Stats => json!({ "dirs": cache.stats()? }),
+                CacheAction::Gc { dry_run } => json!({ "dirs": cache.gc(Utc::now(), dry_run)? }),
+            };
+            println!("{}", serde_json::to_string_pretty(&output)?);
+        }
     }
 
     Ok(())
 }
 
+fn new_aw_client(cfg: &Config) -> AwClient {
+    AwClient::with_config(cfg.aw.base_url.clone(), cfg.aw.transport.clone())
+}
+
+fn new_omni_client(cfg: &Config) -> OmniClient {
+    OmniClient::with_config(cfg.omni.base_url.clone(), cfg.omni.transport.clone())
+}
+
 fn load_config(path: &str) -> Result<Config> {
     let text = fs::read_to_string(Path::new(path))
         .with_context(|| format!("read config failed: {}", path))?;
@@ -92,29 +334,35 @@ fn load_config(path: &str) -> Result<Config> {
 }
 
 fn health_report(cfg: &Config) -> Value {
-    let aw_client = AwClient::new(cfg.aw.base_url.clone());
-    let omni_client = OmniClient::new(cfg.omni.base_url.clone());
+    let aw_client = new_aw_client(cfg);
+    let omni_client = new_omni_client(cfg);
 
     let aw_info = match aw_client.get_info() {
         Ok(value) => json!({ "ok": true, "info": value }),
         Err(err) => json!({ "ok": false, "error": err.to_string() }),
     };
 
-    let omni_probe = match omni_client.probe() {
-        Ok(value) => json!({ "ok": true, "probe": value }),
-        Err(err) => json!({ "ok": false, "error": err.to_string() }),
-    };
-
-    let sidecar_ready = omni_probe
-        .get("ok")
-        .and_then(|v| v.as_bool())
-        .unwrap_or(false);
-
-    let preflight_ok = omni_probe
-        .get("probe")
-        .and_then(|v| v.get("preflight_ok"))
-        .and_then(|v| v.as_bool())
+    let sidecar = omni_client.status();
+    let sidecar_ready = sidecar.as_ref().map(|s| s.ready).unwrap_or(false);
+    let preflight_ok = sidecar
+        .as_ref()
+        .ok()
+        .and_then(|s| s.preflight_ok)
         .unwrap_or(sidecar_ready);
+    let omni_probe = match &sidecar {
+        Ok(status) => json!({
+            "ok": true,
+            "ready": status.ready,
+            "reason": status.reason,
+            "status": status,
+        }),
+        Err(err) => json!({
+            "ok": false,
+            "ready": false,
+            "reason": err.kind(),
+            "error": err.to_string(),
+        }),
+    };
 
     let (protected_env_ok, protected_diff_count) = protected_env_status(cfg);
 
@@ -161,25 +409,102 @@ fn compare_protect_snapshots(pre: &Value, post: &Value) -> i64 {
     diff
 }
 
-fn build_nowframe(cfg: &Config, reason: &str) -> NowFrame {
-    let aw_client = AwClient::new(cfg.aw.base_url.clone());
-    let omni_client = OmniClient::new(cfg.omni.base_url.clone());
+fn nowframe_store(cfg: &Config) -> NowFrameStore {
+    NowFrameStore::new(&cfg.paths.data_nowframes, cfg.nowframes.clone())
+}
+
+fn cache_manager(cfg: &Config) -> CacheManager {
+    CacheManager::new(
+        &cfg.paths.cache_screens,
+        &cfg.paths.cache_thumbs,
+        &cfg.paths.cache_tmp,
+        &cfg.cache,
+    )
+}
+
+fn new_redactor(cfg: &Config) -> Result<Redactor> {
+    Redactor::new(&cfg.redaction).context("invalid [redaction] config")
+}
 
-    let aw_info = aw_client.get_info().ok();
-    let aw_buckets = aw_client.get_buckets().ok();
+fn build_nowframe(cfg: &Config, reason: &str, redactor: &Redactor) -> NowFrame {
+    let aw_client = new_aw_client(cfg);
+    let activity = aw_client.current_activity().ok();
+    assemble_nowframe(cfg, reason, activity, None, redactor)
+}
+
+/// Builds a NowFrame with privacy redaction already applied, ready to store or print.
+fn assemble_nowframe(
+    cfg: &Config,
+    reason: &str,
+    activity: Option<CurrentActivity>,
+    trigger: Option<Decision>,
+    redactor: &Redactor,
+) -> NowFrame {
+    let omni_client = new_omni_client(cfg);
     let omni_probe = omni_client.probe().ok();
+    let screen = ScreenSummary::from_latest(
+        Path::new(&cfg.paths.cache_screens),
+        ScreenSummary::DEFAULT_MAX_AGE,
+        Utc::now(),
+    );
+
+    let mut nowframe = NowFrame {
+        activity,
+        omni_probe,
+        trigger,
+        screen,
+        ..NowFrame::new(reason)
+    };
+    nowframe.redact(redactor);
+    nowframe
+}
 
-    let trigger_input = TriggerInput {
-        reason: reason.to_string(),
-        hint: Some(0.6),
+/// Mirrors the MCP `screen.bundle` heartbeat; the frame fields are null when no
+/// recent parsed screen was attached to the NowFrame.
+fn emit_nowframe_heartbeat(cfg: &Config, nowframe: &NowFrame) -> Result<()> {
+    let hb = match cfg.aw_heartbeat.as_ref() {
+        Some(hb) if hb.enabled => hb,
+        _ => return Ok(()),
     };
-    let _score = score(&trigger_input);
 
-    NowFrame {
-        timestamp: Utc::now().to_rfc3339(),
-        reason: reason.to_string(),
-        aw_info,
-        aw_buckets,
-        omni_probe,
-    }
+    let omni_ready = nowframe
+        .omni_probe
+        .as_ref()
+        .and_then(|v| v.get("ready").or_else(|| v.get("ok")))
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+
+    let screen = nowframe.screen.as_ref();
+    let mut data = Map::new();
+    data.insert("source".to_string(), json!("daemon"));
+    data.insert("reason".to_string(), json!(nowframe.reason));
+    data.insert(
+        "app".to_string(),
+        json!(nowframe.activity.as_ref().and_then(|a| a.app.clone())),
+    );
+    data.insert("omni_ready".to_string(), json!(omni_ready));
+    data.insert("frame_id".to_string(), json!(screen.map(|s| &s.frame_id)));
+    data.insert(
+        "element_count".to_string(),
+        json!(screen.map(|s| s.element_count)),
+    );
+    data.insert(
+        "text_count".to_string(),
+        json!(screen.map(|s| s.text_count)),
+    );
+    data.insert(
+        "icon_count".to_string(),
+        json!(screen.map(|s| s.icon_count)),
+    );
+    data.insert(
+        "dominant_text".to_string(),
+        json!(screen.and_then(|s| s.dominant_text.as_ref())),
+    );
+
+    let client = new_aw_client(cfg);
+    let bucket_id = client.ensure_omni_bucket()?;
+    let mut event = Event::new(Utc::now(), data);
+    new_redactor(cfg)?.event(&mut event);
+    client.heartbeat(&bucket_id, &event, hb.pulsetime_secs)?;
+    Ok(())
 }
@@ -0,0 +1,241 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Context, Result};
+use chrono::Utc;
+use omni_client::OmniClient;
+use serde_json::{json, Value};
+
+use crate::{new_omni_client, Config};
+
+const PID_FILE: &str = "omni_sidecar.pid";
+const LOG_FILE: &str = "omni_sidecar.log";
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+const RESTART_BACKOFF_MIN: Duration = Duration::from_secs(1);
+const RESTART_BACKOFF_MAX: Duration = Duration::from_secs(60);
+// A child that stayed up this long is considered healthy; its crash resets the backoff.
+const STABLE_UPTIME: Duration = Duration::from_secs(60);
+// argparse usage errors and the entry script's missing prerequisites; a restart
+// would fail the same way.
+const EXIT_USAGE: i32 = 2;
+
+pub struct SupervisorOptions {
+    pub mode: Option<String>,
+    pub ready_timeout: Duration,
+    pub max_restarts: Option<u32>,
+}
+
+struct LaunchSpec {
+    python: String,
+    script: String,
+    mode: String,
+    host: String,
+    port: u16,
+    real_repo: Option<String>,
+    weights_root: Option<String>,
+}
+
+pub fn supervise(cfg: &Config, options: &SupervisorOptions) -> Result<()> {
+    let spec = launch_spec(cfg, options)?;
+    let pid_path = PathBuf::from(&cfg.paths.runtime_pid).join(PID_FILE);
+    let log_path = PathBuf::from(&cfg.paths.runtime_logs).join(LOG_FILE);
+    let omni_client = new_omni_client(cfg);
+
+    let mut restarts = 0u32;
+    let mut backoff = RESTART_BACKOFF_MIN;
+    loop {
+        // Another sidecar on the same port would answer our probes while ours fails to bind.
+        if omni_client.status().is_ok() {
+            return Err(anyhow!(
+                "a sidecar is already listening on {}",
+                cfg.omni.base_url
+            ));
+        }
+        let mut child = spawn(&spec, &log_path)?;
+        let started = Instant::now();
+        write_pid(&pid_path, child.id())?;
+        emit(json!({
+            "event": "spawned",
+            "pid": child.id(),
+            "mode": spec.mode,
+            "log_path": log_path.to_string_lossy(),
+        }));
+
+        let status = match wait_ready(&omni_client, &mut child, options.ready_timeout)? {
+            Some(status) => {
+                emit(json!({ "event": "exited_before_ready", "status": status.code() }));
+                status
+            }
+            None => {
+                let status = child.wait().context("wait for sidecar failed")?;
+                emit(json!({
+                    "event": "exited",
+                    "status": status.code(),
+                    "uptime_secs": started.elapsed().as_secs(),
+                }));
+                status
+            }
+        };
+        let _ = fs::remove_file(&pid_path);
+
+        if status.code() == Some(EXIT_USAGE) {
+            return Err(anyhow!(
+                "sidecar exited with code {} (bad arguments or missing prerequisites); \
+                 not restarting, see {}",
+                EXIT_USAGE,
+                log_path.display()
+            ));
+        }
+
+        if started.elapsed() >= STABLE_UPTIME {
+            backoff = RESTART_BACKOFF_MIN;
+        }
+        if let Some(max) = options.max_restarts {
+            if restarts >= max {
+                return Err(anyhow!("sidecar exited; restart limit {} reached", max));
+            }
+        }
+        restarts += 1;
+        emit(json!({
+            "event": "restarting",
+            "attempt": restarts,
+            "backoff_ms": backoff.as_millis() as u64,
+        }));
+        thread::sleep(backoff);
+        backoff = (backoff * 2).min(RESTART_BACKOFF_MAX);
+    }
+}
+
+fn launch_spec(cfg: &Config, options: &SupervisorOptions) -> Result<LaunchSpec> {
+    let sidecar = cfg
+        .sidecar
+        .as_ref()
+        .ok_or_else(|| anyhow!("config has no [sidecar] section"))?;
+    let python = sidecar
+        .python
+        .clone()
+        .ok_or_else(|| anyhow!("[sidecar] python is not set"))?;
+    let script = sidecar
+        .script
+        .clone()
+        .ok_or_else(|| anyhow!("[sidecar] script is not set"))?;
+    let mode = options
+        .mode
+        .clone()
+        .or_else(|| sidecar.sidecar_mode.clone())
+        .unwrap_or_else(|| "mock".to_string());
+    let (host, port) = host_port(&cfg.omni.base_url)?;
+    Ok(LaunchSpec {
+        python,
+        script,
+        mode,
+        host,
+        port,
+        real_repo: sidecar.real_repo.clone(),
+        weights_root: sidecar.weights_root.clone(),
+    })
+}
+
+fn host_port(base_url: &str) -> Result<(String, u16)> {
+    let without_scheme = base_url
+        .split_once("://")
+        .map(|(_, rest)| rest)
+        .unwrap_or(base_url);
+    let authority = without_scheme.split('/').next().unwrap_or("");
+    match authority.rsplit_once(':') {
+        Some((host, port)) => {
+            let port = port
+                .parse::<u16>()
+                .with_context(|| format!("invalid port in omni.base_url: {}", base_url))?;
+            Ok((host.to_string(), port))
+        }
+        None if !authority.is_empty() => Ok((authority.to_string(), 80)),
+        None => Err(anyhow!("invalid omni.base_url: {}", base_url)),
+    }
+}
+
+fn spawn(spec: &LaunchSpec, log_path: &Path) -> Result<Child> {
+    if let Some(parent) = log_path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("create log dir failed: {}", parent.display()))?;
+    }
+    let log = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log_path)
+        .with_context(|| format!("open sidecar log failed: {}", log_path.display()))?;
+    let log_err = log.try_clone().context("clone sidecar log handle failed")?;
+
+    let mut command = Command::new(&spec.python);
+    command
+        .arg(&spec.script)
+        .arg("--mode")
+        .arg(&spec.mode)
+        .arg("--host")
+        .arg(&spec.host)
+        .arg("--port")
+        .arg(spec.port.to_string());
+    if let Some(real_repo) = &spec.real_repo {
+        command.arg("--real-repo").arg(real_repo);
+    }
+    if let Some(weights_root) = &spec.weights_root {
+        command.arg("--weights-root").arg(weights_root);
+    }
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::from(log))
+        .stderr(Stdio::from(log_err));
+
+    command
+        .spawn()
+        .with_context(|| format!("spawn sidecar failed: {} {}", spec.python, spec.script))
+}
+
+/// Polls `/probe` until the sidecar reports ready. Returns the exit status if the child
+/// died first; a sidecar that stays up but never becomes ready is left running.
+fn wait_ready(
+    omni_client: &OmniClient,
+    child: &mut Child,
+    timeout: Duration,
+) -> Result<Option<ExitStatus>> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(status) = child.try_wait().context("poll sidecar failed")? {
+            return Ok(Some(status));
+        }
+        let reason = match omni_client.status() {
+            Ok(status) if status.ready => {
+                emit(json!({ "event": "ready", "mode": status.mode, "model": status.model }));
+                return Ok(None);
+            }
+            Ok(status) => status.reason.as_str(),
+            Err(err) => err.kind(),
+        };
+        if Instant::now() >= deadline {
+            emit(json!({
+                "event": "not_ready",
+                "reason": reason,
+                "timeout_secs": timeout.as_secs(),
+            }));
+            return Ok(None);
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+fn write_pid(path: &Path, pid: u32) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("create pid dir failed: {}", parent.display()))?;
+    }
+    fs::write(path, pid.to_string())
+        .with_context(|| format!("write pid file failed: {}", path.display()))
+}
+
+fn emit(mut event: Value) {
+    event["ts"] = Value::String(Utc::now().to_rfc3339());
+    println!("{}", event);
+}
@@ -0,0 +1,183 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use aw_client::HostBuckets;
+use nowframe_core::activity_signal;
+use redaction::{Field, Redactor};
+use serde::Deserialize;
+use serde_json::json;
+use trigger_engine::{GateConfig, TriggerEngine, TriggerInput, TriggerState};
+
+use crate::{
+    assemble_nowframe, emit_nowframe_heartbeat, new_aw_client, new_redactor, nowframe_store, Config,
+};
+
+#[derive(Debug, Deserialize)]
+pub struct WatchConfig {
+    #[serde(default = "default_poll_interval_secs")]
+    pub poll_interval_secs: u64,
+    #[serde(default = "default_threshold")]
+    pub threshold: f64,
+}
+
+impl Default for WatchConfig {
+    fn default() -> Self {
+        Self {
+            poll_interval_secs: default_poll_interval_secs(),
+            threshold: default_threshold(),
+        }
+    }
+}
+
+fn default_poll_interval_secs() -> u64 {
+    5
+}
+
+fn default_threshold() -> f64 {
+    0.7
+}
+
+pub struct WatchOptions {
+    pub poll_interval: Duration,
+    pub threshold: f64,
+    pub rules_path: Option<String>,
+    pub max_polls: Option<u64>,
+}
+
+pub fn run(cfg: &Config, options: &WatchOptions) -> Result<()> {
+    let aw_client = new_aw_client(cfg);
+    let store = nowframe_store(cfg);
+    let redactor = new_redactor(cfg)?;
+    let state_path = trigger_state_path(cfg);
+    let gate = GateConfig {
+        fire_above: options.threshold,
+        rearm_below: cfg.trigger.rearm_below.min(options.threshold),
+        rearm_after_secs: cfg.trigger.rearm_after_secs,
+        debounce_secs: cfg.trigger.debounce_secs,
+        min_interval_secs: cfg.trigger.min_interval_secs,
+        max_per_hour: cfg.trigger.max_per_hour,
+    };
+    let mut engine = TriggerEngine::from_path(options.rules_path.as_deref().map(Path::new))
+        .context("load trigger rules failed")?
+        .with_gate(gate)
+        .with_state(load_trigger_state(&state_path));
+    let mut buckets: Option<HostBuckets> = None;
+    let mut polls = 0u64;
+
+    loop {
+        if buckets.is_none() {
+            buckets = aw_client.discover_buckets().ok();
+        }
+        let activity = match buckets.as_ref() {
+            Some(b) => match aw_client.current_activity_for(b) {
+                Ok(activity) => Some(activity),
+                Err(err) => {
+                    eprintln!("watch: read activity failed: {}", err);
+                    // Buckets may have been recreated (e.g. watcher restart); rediscover.
+                    buckets = None;
+                    None
+                }
+            },
+            None => None,
+        };
+
+        if let Some(activity) = activity {
+            let current = activity_signal(&activity);
+            let input = TriggerInput {
+                now: activity.observed_at,
+                reason: None,
+                current: current.clone(),
+                previous: engine.state_mut().last_signal.take(),
+                dwell_secs: activity.window_duration_secs,
+            };
+            let decision = engine.step(&input);
+            engine.state_mut().last_signal = Some(current);
+            if decision.fired {
+                let reason = decision.reason();
+                let score = decision.score;
+                let nowframe =
+                    assemble_nowframe(cfg, &reason, Some(activity), Some(decision), &redactor);
+                match store.save(&nowframe) {
+                    Ok(entry) => {
+                        if let Err(err) = emit_nowframe_heartbeat(cfg, &nowframe) {
+                            eprintln!("aw heartbeat failed: {}", err);
+                        }
+                        println!(
+                            "{}",
+                            json!({
+                                "event": "nowframe",
+                                "ts": nowframe.timestamp,
+                                "reason": nowframe.reason,
+                                "score": score,
+                                "id": entry.id,
+                                "path": entry.path.to_string_lossy(),
+                            })
+                        );
+                    }
+                    // A full or read-only disk must not stop the watcher; later fires retry.
+                    Err(err) => eprintln!("watch: save nowframe failed: {}", err),
+                }
+            }
+            if let Err(err) = save_trigger_state(&state_path, engine.state(), &redactor) {
+                eprintln!("watch: save trigger state failed: {:#}", err);
+            }
+        }
+
+        polls += 1;
+        if options.max_polls.is_some_and(|max| polls >= max) {
+            return Ok(());
+        }
+        thread::sleep(options.poll_interval);
+    }
+}
+
+fn trigger_state_path(cfg: &Config) -> PathBuf {
+    match cfg.trigger.state_path.as_ref() {
+        Some(path) => PathBuf::from(path),
+        None => Path::new(&cfg.paths.root)
+            .join("runtime")
+            .join("trigger_state.json"),
+    }
+}
+
+fn load_trigger_state(path: &Path) -> TriggerState {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(_) => return TriggerState::default(),
+    };
+    match serde_json::from_str(&text) {
+        Ok(state) => state,
+        Err(err) => {
+            eprintln!(
+                "watch: ignoring unreadable trigger state {}: {}",
+                path.display(),
+                err
+            );
+            TriggerState::default()
+        }
+    }
+}
+
+fn save_trigger_state(path: &Path, state: &TriggerState, redactor: &Redactor) -> Result<()> {
+    // Stored signals only feed change detection; keep raw titles off disk.
+    let mut state = state.clone();
+    let pending = state.pending.as_mut().and_then(|p| p.previous.as_mut());
+    for signal in state.last_signal.iter_mut().chain(pending) {
+        let app = signal.app.clone();
+        redactor.option(&mut signal.title, app.as_deref(), Field::Title);
+    }
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("create state dir failed: {}", dir.display()))?;
+    }
+    // Write then rename so a crash mid-write never leaves a truncated state file.
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, serde_json::to_string(&state)?)
+        .with_context(|| format!("write trigger state failed: {}", tmp.display()))?;
+    fs::rename(&tmp, path)
+        .with_context(|| format!("replace trigger state failed: {}", path.display()))?;
+    Ok(())
+}
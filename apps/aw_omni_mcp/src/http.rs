@@ -0,0 +1,411 @@
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::env;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{Cursor, Read, Write};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, bail, Result};
+use serde_json::Value;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::{dispatch_request, error_response, log_line, notify, Config};
+
+/// The single MCP endpoint: POST carries client messages, GET opens the SSE stream.
+const ENDPOINT: &str = "/mcp";
+const MAX_BODY_BYTES: u64 = 8 * 1024 * 1024;
+/// SSE comment sent when there is nothing else to write, so dead streams are noticed.
+const KEEPALIVE: Duration = Duration::from_secs(15);
+const SESSION_HEADER: &str = "Mcp-Session-Id";
+/// Sessions are only ended by `DELETE`; past this many the least recently used
+/// one is dropped, together with its subscriptions.
+const MAX_SESSIONS: usize = 64;
+
+/// Live session ids and when each was last used.
+static SESSIONS: OnceLock<Mutex<HashMap<String, Instant>>> = OnceLock::new();
+static SESSION_COUNTER: AtomicU64 = AtomicU64::new(0);
+/// Requests handled at once, open SSE streams included; beyond this new
+/// requests get `503` instead of another thread.
+const MAX_IN_FLIGHT: usize = 48;
+/// Open SSE streams. A dropped stream is only noticed when a keepalive write
+/// fails, so streams get their own smaller budget and cannot starve POSTs.
+const MAX_STREAMS: usize = 16;
+static IN_FLIGHT: AtomicUsize = AtomicUsize::new(0);
+static STREAMS: AtomicUsize = AtomicUsize::new(0);
+
+struct Listener {
+    cfg: Config,
+    port: u16,
+    token: String,
+}
+
+/// One unit of a bounded budget, given back on drop.
+struct Slot(&'static AtomicUsize);
+
+impl Slot {
+    fn acquire(counter: &'static AtomicUsize, max: usize) -> Option<Slot> {
+        if counter.fetch_add(1, Ordering::AcqRel) >= max {
+            counter.fetch_sub(1, Ordering::AcqRel);
+            return None;
+        }
+        Some(Slot(counter))
+    }
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// Serves MCP streamable HTTP on 127.0.0.1 until the process is killed. Each
+/// request runs on its own thread; `shutdown` / `exit` are answered but do not
+/// stop the server, since other clients may still be connected. `initialize`
+/// opens a session (`Mcp-Session-Id`) that scopes resource subscriptions.
+///
+/// Any local process can reach the port, so `MCP_AUTH_TOKEN` is required here
+/// even though it is optional over stdio.
+pub fn serve(cfg: Config, port: u16) -> Result<()> {
+    let token = match env::var("MCP_AUTH_TOKEN") {
+        Ok(value) if !value.trim().is_empty() => value.trim().to_string(),
+        _ => bail!(
+            "--http requires MCP_AUTH_TOKEN; clients send it as Authorization: Bearer <token>"
+        ),
+    };
+    let server = Server::http(("127.0.0.1", port))
+        .map_err(|e| anyhow!("bind 127.0.0.1:{} failed: {}", port, e))?;
+    log_line(&format!("http_listen=127.0.0.1:{}{}", port, ENDPOINT));
+    let listener = Arc::new(Listener { cfg, port, token });
+    for request in server.incoming_requests() {
+        let Some(slot) = Slot::acquire(&IN_FLIGHT, MAX_IN_FLIGHT) else {
+            log_line("http_busy");
+            reply(request, busy());
+            continue;
+        };
+        let listener = Arc::clone(&listener);
+        thread::spawn(move || {
+            let _slot = slot;
+            handle(&listener, request)
+        });
+    }
+    Ok(())
+}
+
+fn handle(listener: &Listener, request: Request) {
+    let method = request.method().clone();
+    let path = request.url().split('?').next().unwrap_or("").to_string();
+    log_line(&format!("http method={} path={}", method, path));
+
+    if path != ENDPOINT {
+        return reply(
+            request,
+            Response::from_string("not found").with_status_code(404),
+        );
+    }
+    if !host_allowed(&request, listener.port) {
+        return reply(
+            request,
+            Response::from_string("forbidden host").with_status_code(403),
+        );
+    }
+    if !origin_allowed(&request) {
+        return reply(
+            request,
+            Response::from_string("forbidden origin").with_status_code(403),
+        );
+    }
+    if !authorized(&request, &listener.token) {
+        let challenge = header("WWW-Authenticate", "Bearer");
+        return reply(
+            request,
+            Response::from_string("unauthorized")
+                .with_status_code(401)
+                .with_header(challenge),
+        );
+    }
+
+    match method {
+        Method::Post => post(&listener.cfg, request),
+        Method::Get if accepts(&request, "text/event-stream") => match known_session(&request) {
+            Ok(session) => stream(request, session),
+            Err(response) => reply(request, response),
+        },
+        Method::Delete => match known_session(&request) {
+            Ok(session) => {
+                end_session(&session);
+                reply(request, Response::empty(204))
+            }
+            Err(response) => reply(request, response),
+        },
+        _ => reply(
+            request,
+            Response::from_string("method not allowed")
+                .with_status_code(405)
+                .with_header(header("Allow", "GET, POST, DELETE")),
+        ),
+    }
+}
+
+/// One JSON-RPC message or a batch. Requests are answered with
+/// `application/json`; a body of only notifications and responses gets 202.
+fn post(cfg: &Config, mut request: Request) {
+    let mut body = Vec::new();
+    let read = request
+        .as_reader()
+        .take(MAX_BODY_BYTES + 1)
+        .read_to_end(&mut body);
+    if let Err(err) = read {
+        log_line(&format!("http_read_error={}", err));
+        return reply(
+            request,
+            Response::from_string("read failed").with_status_code(400),
+        );
+    }
+    if body.len() as u64 > MAX_BODY_BYTES {
+        return reply(
+            request,
+            Response::from_string("payload too large").with_status_code(413),
+        );
+    }
+
+    let parsed: Value = match serde_json::from_slice(&body) {
+        Ok(value) => value,
+        Err(err) => {
+            log_line(&format!("parse_error={}", err));
+            let response = error_response(Value::Null, -32700, &format!("parse error: {}", err));
+            return reply(request, json_response(&response).with_status_code(400));
+        }
+    };
+
+    let messages: Vec<&Value> = match &parsed {
+        Value::Array(batch) => batch.iter().collect(),
+        message => vec![message],
+    };
+    // `initialize` without a session header starts a new session; everything
+    // else must name a live one.
+    let initializes = messages
+        .iter()
+        .any(|m| m.get("method").and_then(|v| v.as_str()) == Some("initialize"));
+    let (session, created) = if initializes && header_value(&request, SESSION_HEADER).is_none() {
+        (new_session(), true)
+    } else {
+        match known_session(&request) {
+            Ok(session) => (session, false),
+            Err(response) => return reply(request, response),
+        }
+    };
+
+    let responses: Vec<Value> = messages
+        .into_iter()
+        .filter_map(|message| dispatch_request(cfg, &session, message).response)
+        .collect();
+    let mut response = match (parsed.is_array(), responses.len()) {
+        (_, 0) => Response::from_string("").with_status_code(202),
+        (false, _) => json_response(&responses[0]),
+        (true, _) => json_response(&Value::Array(responses)),
+    };
+    if created {
+        let id = Header::from_bytes(SESSION_HEADER.as_bytes(), session.as_bytes())
+            .expect("session id is ascii hex");
+        response = response.with_header(id);
+    }
+    reply(request, response);
+}
+
+/// The session's server-initiated messages (resource updates) as an SSE stream.
+/// The stream lives until a write fails, i.e. the client went away, or the
+/// session ends.
+fn stream(request: Request, session: String) {
+    let Some(_slot) = Slot::acquire(&STREAMS, MAX_STREAMS) else {
+        log_line("sse_busy");
+        return reply(request, busy());
+    };
+    let (tx, rx) = mpsc::channel::<Value>();
+    let sink_id = notify::add_sink(
+        &session,
+        Arc::new(move |message| {
+            let _ = tx.send(message.clone());
+        }),
+    );
+    log_line(&format!("sse_open sink={} session={}", sink_id, session));
+
+    let mut writer = request.into_writer();
+    let mut result = writer
+        .write_all(
+            b"HTTP/1.1 200 OK\r\n\
+              Content-Type: text/event-stream\r\n\
+              Cache-Control: no-cache\r\n\
+              Connection: close\r\n\r\n",
+        )
+        .and_then(|_| writer.flush());
+    while result.is_ok() {
+        let event = match rx.recv_timeout(KEEPALIVE) {
+            Ok(message) => format!("event: message\ndata: {}\n\n", message),
+            Err(RecvTimeoutError::Timeout) => ": keepalive\n\n".to_string(),
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+        result = writer
+            .write_all(event.as_bytes())
+            .and_then(|_| writer.flush());
+    }
+
+    notify::remove_sink(sink_id);
+    log_line(&format!("sse_close sink={}", sink_id));
+}
+
+/// The live session named by the request header: 400 when it is missing, 404
+/// when it is unknown or was ended (the client should initialize again).
+fn known_session(request: &Request) -> Result<String, Response<Cursor<Vec<u8>>>> {
+    let id = header_value(request, SESSION_HEADER).ok_or_else(|| {
+        Response::from_string("missing Mcp-Session-Id; send initialize first").with_status_code(400)
+    })?;
+    let mut sessions = sessions().lock().unwrap_or_else(|e| e.into_inner());
+    match sessions.get_mut(id) {
+        Some(last_used) => {
+            *last_used = Instant::now();
+            Ok(id.to_string())
+        }
+        None => Err(Response::from_string("unknown session").with_status_code(404)),
+    }
+}
+
+fn new_session() -> String {
+    // Not a secret (every request is authenticated separately), only unguessable
+    // enough that sessions cannot be confused: std's randomly keyed SipHash over
+    // the clock and a counter.
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    let counter = SESSION_COUNTER.fetch_add(1, Ordering::Relaxed);
+    let mut halves = [0u64; 2];
+    for half in halves.iter_mut() {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u128(nanos);
+        hasher.write_u64(counter);
+        *half = hasher.finish();
+    }
+    let id = format!("{:016x}{:016x}", halves[0], halves[1]);
+
+    let evicted = {
+        let mut sessions = sessions().lock().unwrap_or_else(|e| e.into_inner());
+        let evicted = if sessions.len() >= MAX_SESSIONS {
+            sessions
+                .iter()
+                .min_by_key(|(_, last_used)| **last_used)
+                .map(|(id, _)| id.clone())
+        } else {
+            None
+        };
+        if let Some(old) = &evicted {
+            sessions.remove(old);
+        }
+        sessions.insert(id.clone(), Instant::now());
+        evicted
+    };
+    if let Some(old) = evicted {
+        notify::end_session(&old);
+        log_line(&format!("session_evicted={}", old));
+    }
+    log_line(&format!("session_open={}", id));
+    id
+}
+
+fn end_session(session: &str) {
+    sessions()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .remove(session);
+    notify::end_session(session);
+    log_line(&format!("session_close={}", session));
+}
+
+fn sessions() -> &'static Mutex<HashMap<String, Instant>> {
+    SESSIONS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Every request needs `Authorization: Bearer <MCP_AUTH_TOKEN>`;
+/// `params.auth_token` is not consulted over HTTP.
+fn authorized(request: &Request, expected: &str) -> bool {
+    header_value(request, "Authorization")
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|token| constant_time_eq(token.trim().as_bytes(), expected.as_bytes()))
+}
+
+/// Compares without returning early on the first differing byte, so response
+/// timing does not reveal how much of a guessed token was right.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// A DNS-rebinding page reaches the loopback port under its own host name, and
+/// same-origin requests carry no `Origin`; only the loopback names are served.
+fn host_allowed(request: &Request, port: u16) -> bool {
+    let Some(host) = header_value(request, "Host") else {
+        return false;
+    };
+    let host = host.trim();
+    ["127.0.0.1", "localhost", "[::1]"]
+        .iter()
+        .any(|name| host == format!("{}:{}", name, port))
+}
+
+/// Browsers send `Origin`; only local pages may talk to the server, which
+/// keeps DNS-rebinding pages out. Non-browser clients send none.
+fn origin_allowed(request: &Request) -> bool {
+    let origin = match header_value(request, "Origin") {
+        Some(origin) => origin,
+        None => return true,
+    };
+    let host = origin
+        .split_once("://")
+        .map(|(_, rest)| rest)
+        .unwrap_or(origin);
+    let host = match host.strip_prefix('[') {
+        Some(v6) => v6.split(']').next().unwrap_or(""),
+        None => host.split(':').next().unwrap_or(""),
+    };
+    matches!(host, "127.0.0.1" | "localhost" | "::1")
+}
+
+fn accepts(request: &Request, mime: &str) -> bool {
+    header_value(request, "Accept")
+        .map(|accept| accept.contains(mime))
+        .unwrap_or(false)
+}
+
+fn header_value<'a>(request: &'a Request, name: &'static str) -> Option<&'a str> {
+    request
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv(name))
+        .map(|h| h.value.as_str())
+}
+
+fn header(name: &'static str, value: &'static str) -> Header {
+    Header::from_bytes(name.as_bytes(), value.as_bytes()).expect("static header is valid")
+}
+
+fn busy() -> Response<Cursor<Vec<u8>>> {
+    Response::from_string("busy")
+        .with_status_code(503)
+        .with_header(header("Retry-After", "1"))
+}
+
+fn json_response(value: &Value) -> Response<Cursor<Vec<u8>>> {
+    let body = serde_json::to_vec(value).unwrap_or_default();
+    Response::from_data(body).with_header(header("Content-Type", "application/json"))
+}
+
+fn reply<R: Read>(request: Request, response: Response<R>) {
+    if let Err(err) = request.respond(response) {
+        log_line(&format!("http_write_error={}", err));
+    }
+}
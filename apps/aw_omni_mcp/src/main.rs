@@ -1,26 +1,39 @@
 use std::env;
 use std::fs;
-use std::io::{self, BufRead, Write};
+use std::io::{self, BufRead, Cursor, Write};
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::{Mutex, OnceLock};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
 
 use anyhow::{Context, Result};
-use aw_client::AwClient;
+use aw_client::{AwClient, Event, HostBuckets};
 use base64::engine::general_purpose::STANDARD as BASE64_ENGINE;
 use base64::Engine;
 use chrono::Utc;
 use clap::Parser;
-use image::{DynamicImage, Rgba, RgbaImage};
+use http_transport::TransportConfig;
+use image::{DynamicImage, GenericImageView, ImageFormat, Rgba, RgbaImage};
 use imageproc::drawing::{draw_filled_rect_mut, draw_hollow_rect_mut};
 use imageproc::rect::Rect;
-use nowframe_core::NowFrame;
-use omni_client::OmniClient;
+use nowframe_core::{
+    activity_signal, segment, CacheManager, CachePolicy, ListQuery, NowFrame, NowFrameStore,
+    RetentionPolicy, ScreenSummary, SegmenterConfig, StoreError, MAX_EPISODE_HOURS,
+};
+use omni_client::{ClientError, ElementKind, OmniClient, ParsedElement, PixelBox};
+use redaction::{Field, PixelMode, RedactionConfig, Redactor};
 use serde::{Deserialize, Serialize};
-use serde_json::{json, Value};
+use serde_json::{json, Map, Value};
+use trigger_engine::{GateConfig, TriggerEngine, TriggerInput, TriggerState};
 #[cfg(windows)]
 use xcap::{Monitor, Window};
 
+mod http;
+mod notify;
+mod prompts;
+mod resources;
+mod tools;
+
 static FRAME_COUNTER: AtomicU64 = AtomicU64::new(0);
 static LATEST_BUNDLE: OnceLock<Mutex<Option<LatestBundle>>> = OnceLock::new();
 const MAX_IMAGE_BYTES: u64 = 6 * 1024 * 1024;
@@ -32,11 +45,19 @@ enum WireMode {
     LineJson,
 }
 
+struct StdioOut {
+    writer: io::BufWriter<io::Stdout>,
+    mode: WireMode,
+}
+
 #[derive(Parser, Debug)]
 #[command(name = "aw_omni_mcp")]
 struct Cli {
     #[arg(long, default_value = "config/local.wsl.toml")]
     config: String,
+    /// Serve MCP streamable HTTP on 127.0.0.1:<PORT> instead of stdio.
+    #[arg(long, value_name = "PORT")]
+    http: Option<u16>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -44,20 +65,82 @@ struct Config {
     aw: EndpointConfig,
     omni: EndpointConfig,
     paths: PathsConfig,
+    #[allow(dead_code)]
     sidecar: Option<SidecarConfig>,
+    aw_heartbeat: Option<HeartbeatConfig>,
+    #[serde(default)]
+    watch: WatchConfig,
+    #[serde(default)]
+    trigger: TriggerConfig,
+    #[serde(default)]
+    episodes: EpisodesConfig,
+    #[serde(default)]
+    redaction: RedactionConfig,
+    #[serde(default)]
+    cache: CachePolicy,
 }
 
 #[derive(Debug, Deserialize)]
 struct EndpointConfig {
     base_url: String,
+    #[serde(flatten)]
+    transport: TransportConfig,
+}
+
+#[derive(Debug, Deserialize)]
+struct HeartbeatConfig {
+    #[serde(default)]
+    enabled: bool,
+    #[serde(default = "default_pulsetime_secs")]
+    pulsetime_secs: f64,
+}
+
+fn default_pulsetime_secs() -> f64 {
+    60.0
+}
+
+#[derive(Debug, Deserialize)]
+struct WatchConfig {
+    #[serde(default = "default_threshold")]
+    threshold: f64,
+}
+
+impl Default for WatchConfig {
+    fn default() -> Self {
+        Self {
+            threshold: default_threshold(),
+        }
+    }
 }
 
+fn default_threshold() -> f64 {
+    0.7
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct TriggerConfig {
+    rules_path: Option<String>,
+    /// The daemon's persisted gate state; defaults to `<paths.root>/runtime/trigger_state.json`.
+    state_path: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct EpisodesConfig {
+    /// Opt-in consent for deriving episodes (DATA_GOVERNANCE.md).
+    #[serde(default)]
+    enabled: bool,
+    #[serde(flatten)]
+    segmenter: SegmenterConfig,
+}
+
+#[allow(dead_code)]
 #[derive(Debug, Deserialize)]
 struct SidecarConfig {
     python: Option<String>,
     script: Option<String>,
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Deserialize)]
 struct PathsConfig {
     root: String,
@@ -83,6 +166,8 @@ struct LatestBundle {
     mask_path: Option<String>,
     json_path: Option<String>,
     som_path: Option<String>,
+    #[serde(default)]
+    redacted_path: Option<String>,
 }
 
 #[derive(Debug)]
@@ -93,18 +178,22 @@ struct CaptureMeta {
     width: u32,
     height: u32,
     cursor_included: bool,
+    // Strict screenshot redaction: the capture stays in memory until it has been
+    // parsed and redacted, and only then is written to `raw_path`.
+    pending: Option<RgbaImage>,
 }
 
 #[derive(Debug)]
 struct ParseMeta {
     frame_id: String,
     raw_path: PathBuf,
-    elements: Vec<Value>,
+    elements: Vec<ParsedElement>,
     latency_ms: i64,
     has_text: bool,
     has_icon: bool,
     som_path: Option<PathBuf>,
-    response: Value,
+    redacted_path: Option<PathBuf>,
+    redacted_regions: usize,
 }
 
 fn main() -> Result<()> {
@@ -125,10 +214,32 @@ fn main() -> Result<()> {
         }
     };
 
+    notify::spawn_watcher(latest_path(&cfg), PathBuf::from(&cfg.paths.data_nowframes));
+    if let Some(port) = cli.http {
+        return http::serve(cfg, port);
+    }
+
     let stdin = io::stdin();
     let mut reader = io::BufReader::new(stdin.lock());
-    let stdout = io::stdout();
-    let mut writer = io::BufWriter::new(stdout.lock());
+    // Shared with the notification watcher, which writes server-initiated messages
+    // in the wire mode of the last request.
+    let out = Arc::new(Mutex::new(StdioOut {
+        writer: io::BufWriter::new(io::stdout()),
+        mode: WireMode::LineJson,
+    }));
+    let sink_out = Arc::clone(&out);
+    notify::add_sink(notify::STDIO_SESSION, Arc::new(move |message| {
+        let mut out = sink_out.lock().unwrap_or_else(|e| e.into_inner());
+        let mode = out.mode;
+        if let Err(err) = write_response(&mut out.writer, message, mode) {
+            log_line(&format!("notify_write_error={}", err));
+        }
+    }));
+    let send = |response: &Value, mode: WireMode| -> io::Result<()> {
+        let mut out = out.lock().unwrap_or_else(|e| e.into_inner());
+        out.mode = mode;
+        write_response(&mut out.writer, response, mode)
+    };
 
     let mut shutdown_requested = false;
 
@@ -151,7 +262,7 @@ fn main() -> Result<()> {
             Err(err) => {
                 log_line(&format!("parse_error={}", err));
                 let response = error_response(Value::Null, -32700, &format!("parse error: {}", err));
-                write_response(&mut writer, &response, wire_mode)?;
+                send(&response, wire_mode)?;
                 continue;
             }
         };
@@ -162,14 +273,14 @@ fn main() -> Result<()> {
         if let Err(message) = validate_auth(&parsed) {
             if !is_notification {
                 let response = error_response(id, -32001, &message);
-                write_response(&mut writer, &response, wire_mode)?;
+                send(&response, wire_mode)?;
             }
             continue;
         }
 
-        let outcome = dispatch_request(&cfg, &parsed);
+        let outcome = dispatch_request(&cfg, notify::STDIO_SESSION, &parsed);
         if let Some(response) = outcome.response {
-            write_response(&mut writer, &response, wire_mode)?;
+            send(&response, wire_mode)?;
         }
         if outcome.shutdown {
             shutdown_requested = true;
@@ -185,6 +296,14 @@ fn main() -> Result<()> {
     Ok(())
 }
 
+fn new_aw_client(cfg: &Config) -> AwClient {
+    AwClient::with_config(cfg.aw.base_url.clone(), cfg.aw.transport.clone())
+}
+
+fn new_omni_client(cfg: &Config) -> OmniClient {
+    OmniClient::with_config(cfg.omni.base_url.clone(), cfg.omni.transport.clone())
+}
+
 fn load_config(path: &str) -> Result<Config> {
     let text = fs::read_to_string(Path::new(path))
         .with_context(|| format!("read config failed: {}", path))?;
@@ -216,23 +335,6 @@ fn validate_auth(parsed: &Value) -> Result<(), String> {
     }
 }
 
-fn tool_definition() -> Value {
-    json!({
-        "name": "screen.bundle",
-        "description": "Capture, parse, and annotate the screen",
-        "inputSchema": {
-            "type": "object",
-            "properties": {
-                "mode": { "type": "string" },
-                "format": { "type": "string", "enum": ["png"] },
-                "with_cursor": { "type": "boolean" },
-                "include_b64": { "type": "boolean" }
-            },
-            "required": []
-        }
-    })
-}
-
 fn init_log() {
     let path = env::var("MCP_LOG_PATH")
         .ok()
@@ -281,13 +383,103 @@ fn result_response(id: Value, result: Value) -> Value {
     })
 }
 
+#[derive(Debug)]
+struct RpcError {
+    code: i64,
+    message: String,
+    data: Option<Value>,
+}
+
+impl RpcError {
+    /// `-32602`: missing, malformed or out-of-range params.
+    fn invalid_request(message: impl ToString) -> Self {
+        ClientError::invalid_request(message).into()
+    }
+
+    /// `-32020`: the referenced frame, variant or NowFrame does not exist.
+    fn not_found(message: impl Into<String>) -> Self {
+        RpcError {
+            code: -32020,
+            message: message.into(),
+            data: Some(json!({ "kind": "not_found" })),
+        }
+    }
+
+    /// `-32021`: the feature needs opt-in consent in config.
+    fn consent_required(message: impl Into<String>) -> Self {
+        RpcError {
+            code: -32021,
+            message: message.into(),
+            data: Some(json!({ "kind": "consent_required" })),
+        }
+    }
+}
+
+impl From<String> for RpcError {
+    fn from(message: String) -> Self {
+        RpcError {
+            code: -32000,
+            message,
+            data: None,
+        }
+    }
+}
+
+impl From<ClientError> for RpcError {
+    fn from(err: ClientError) -> Self {
+        let (code, detail) = match &err {
+            ClientError::Unreachable { url, .. } => (-32010, json!({ "url": url })),
+            ClientError::Timeout { url } => (-32011, json!({ "url": url })),
+            ClientError::HttpStatus { code, body } => {
+                (-32012, json!({ "status": code, "body": body }))
+            }
+            ClientError::InvalidJson { .. } => (-32013, json!({})),
+            ClientError::NotReady { reason } => (-32014, json!({ "reason": reason })),
+            ClientError::PayloadTooLarge { limit } => (-32015, json!({ "limit": limit })),
+            ClientError::InvalidRequest { .. } => (-32602, json!({})),
+        };
+        let mut data = detail;
+        data["kind"] = Value::String(err.kind().to_string());
+        RpcError {
+            code,
+            message: err.to_string(),
+            data: Some(data),
+        }
+    }
+}
+
+impl From<StoreError> for RpcError {
+    fn from(err: StoreError) -> Self {
+        let code = match &err {
+            StoreError::NotFound(_) => -32020,
+            StoreError::InvalidId(_) => -32602,
+            StoreError::Io { .. } | StoreError::Json { .. } => -32000,
+        };
+        RpcError {
+            code,
+            message: err.to_string(),
+            data: Some(json!({ "kind": err.kind() })),
+        }
+    }
+}
+
+fn rpc_error_response(id: Value, err: &RpcError) -> Value {
+    let mut response = error_response(id, err.code, &err.message);
+    if let Some(data) = &err.data {
+        response["error"]["data"] = data.clone();
+    }
+    response
+}
+
 struct DispatchOutcome {
     response: Option<Value>,
     shutdown: bool,
     exit: bool,
 }
 
-fn dispatch_request(cfg: &Config, parsed: &Value) -> DispatchOutcome {
+/// `session` scopes resource subscriptions: `notify::STDIO_SESSION` for stdio,
+/// the `Mcp-Session-Id` over HTTP.
+fn dispatch_request(cfg: &Config, session: &str, parsed: &Value) -> DispatchOutcome {
     let id_opt = parsed.get("id").cloned();
     let id = id_opt.clone().unwrap_or(Value::Null);
     let is_notification = id_opt.is_none();
@@ -322,7 +514,8 @@ fn dispatch_request(cfg: &Config, parsed: &Value) -> DispatchOutcome {
                 "serverInfo": { "name": "aw_omni_mcp", "version": "0.1.0" },
                 "capabilities": {
                     "tools": { "listChanged": false },
-                    "resources": { "listChanged": false }
+                    "resources": { "subscribe": true, "listChanged": false },
+                    "prompts": { "listChanged": false }
                 }
             });
             DispatchOutcome {
@@ -354,7 +547,8 @@ fn dispatch_request(cfg: &Config, parsed: &Value) -> DispatchOutcome {
             exit: true,
         },
         "tools/list" => {
-            let result = json!({ "tools": [tool_definition()] });
+            let definitions: Vec<Value> = tools::TOOLS.iter().map(|tool| tool.definition()).collect();
+            let result = json!({ "tools": definitions });
             DispatchOutcome {
                 response: if is_notification {
                     None
@@ -375,14 +569,19 @@ fn dispatch_request(cfg: &Config, parsed: &Value) -> DispatchOutcome {
             }
             let name = params.get("name").and_then(|v| v.as_str()).unwrap_or("");
             let args = params.get("arguments").cloned().unwrap_or(Value::Null);
-            if name != "screen.bundle" {
-                return DispatchOutcome {
-                    response: Some(error_response(id, -32601, "unknown tool")),
-                    shutdown: false,
-                    exit: false,
-                };
-            }
-            let result = match screen_bundle(cfg, args) {
+            let tool = match tools::find(name) {
+                Some(tool) => tool,
+                None => {
+                    return DispatchOutcome {
+                        response: Some(error_response(id, -32601, &format!("unknown tool: {}", name))),
+                        shutdown: false,
+                        exit: false,
+                    };
+                }
+            };
+            // Bad params stay protocol errors; a tool that ran and failed reports
+            // it in the result so the model can see and react to the failure.
+            let result = match (tool.handler)(cfg, args) {
                 Ok(value) => {
                     let text = serde_json::to_string(&value).unwrap_or_else(|_| "{}".to_string());
                     Ok(json!({
@@ -390,12 +589,16 @@ fn dispatch_request(cfg: &Config, parsed: &Value) -> DispatchOutcome {
                         "isError": false
                     }))
                 }
-                Err(err) => Err(err),
+                Err(err) if err.code == -32602 => Err(err),
+                Err(err) => Ok(json!({
+                    "content": [{ "type": "text", "text": err.message }],
+                    "isError": true
+                })),
             };
             DispatchOutcome {
                 response: Some(match result {
                     Ok(value) => result_response(id, value),
-                    Err(err) => error_response(id, -32000, &err),
+                    Err(err) => rpc_error_response(id, &err),
                 }),
                 shutdown: false,
                 exit: false,
@@ -409,9 +612,9 @@ fn dispatch_request(cfg: &Config, parsed: &Value) -> DispatchOutcome {
                     exit: false,
                 };
             }
-            let result = match resource_read(cfg, params) {
+            let result = match resources::read(cfg, params) {
                 Ok(value) => result_response(id, value),
-                Err(err) => error_response(id, -32000, &err),
+                Err(err) => rpc_error_response(id, &err),
             };
             DispatchOutcome {
                 response: Some(result),
@@ -419,29 +622,50 @@ fn dispatch_request(cfg: &Config, parsed: &Value) -> DispatchOutcome {
                 exit: false,
             }
         }
-        // legacy JSON-RPC methods
-        "aw.get_state" => wrap_legacy_result(id, is_notification, aw_get_state(cfg)),
-        "nowframe.build" => wrap_legacy_result(id, is_notification, nowframe_build(cfg, params)),
-        "system.health" => wrap_legacy_result(id, is_notification, system_health(cfg)),
-        "screen.capture" => wrap_legacy_result(id, is_notification, screen_capture(cfg, params)),
-        "screen.parse" => wrap_legacy_result(id, is_notification, screen_parse(cfg, params)),
-        "screen.bundle" => wrap_legacy_result(id, is_notification, screen_bundle(cfg, params)),
-        _ => DispatchOutcome {
+        "resources/list" => wrap_legacy_result(id, is_notification, resources::list(cfg)),
+        "resources/subscribe" => wrap_legacy_result(id, is_notification, notify::subscribe(session, &params)),
+        "resources/unsubscribe" => {
+            wrap_legacy_result(id, is_notification, notify::unsubscribe(session, &params))
+        }
+        "resources/templates/list" => DispatchOutcome {
+            response: if is_notification {
+                None
+            } else {
+                Some(result_response(id, resources::templates()))
+            },
+            shutdown: false,
+            exit: false,
+        },
+        "prompts/list" => DispatchOutcome {
             response: if is_notification {
                 None
             } else {
-                Some(error_response(id, -32601, &format!("unknown method: {}", method)))
+                Some(result_response(id, prompts::list()))
             },
             shutdown: false,
             exit: false,
         },
+        "prompts/get" => wrap_legacy_result(id, is_notification, prompts::get(cfg, &params)),
+        // legacy JSON-RPC methods: every tool is also callable by name
+        _ => match tools::find(method) {
+            Some(tool) => wrap_legacy_result(id, is_notification, (tool.handler)(cfg, params)),
+            None => DispatchOutcome {
+                response: if is_notification {
+                    None
+                } else {
+                    Some(error_response(id, -32601, &format!("unknown method: {}", method)))
+                },
+                shutdown: false,
+                exit: false,
+            },
+        },
     }
 }
 
 fn wrap_legacy_result(
     id: Value,
     is_notification: bool,
-    result: Result<Value, String>,
+    result: Result<Value, RpcError>,
 ) -> DispatchOutcome {
     if is_notification {
         return DispatchOutcome {
@@ -452,7 +676,7 @@ fn wrap_legacy_result(
     }
     let response = match result {
         Ok(value) => result_response(id, value),
-        Err(err) => error_response(id, -32000, &err),
+        Err(err) => rpc_error_response(id, &err),
     };
     DispatchOutcome {
         response: Some(response),
@@ -512,8 +736,8 @@ fn read_frame<R: BufRead>(reader: &mut R) -> io::Result<Option<(Vec<u8>, WireMod
                 let header_lower = header.to_ascii_lowercase();
                 if header_lower.starts_with("content-length:") {
                     let value = header
-                        .splitn(2, ':')
-                        .nth(1)
+                        .split_once(':')
+                        .map(|(_, tail)| tail)
                         .unwrap_or("")
                         .trim();
                     match value.parse::<usize>() {
@@ -567,81 +791,241 @@ fn write_response<W: Write>(writer: &mut W, response: &Value, mode: WireMode) ->
     Ok(())
 }
 
-fn aw_get_state(cfg: &Config) -> Result<Value, String> {
-    let client = AwClient::new(cfg.aw.base_url.clone());
-    let info = client.get_info().map_err(|e| e.to_string())?;
-    let buckets = client.get_buckets().map_err(|e| e.to_string())?;
+fn aw_get_state(cfg: &Config) -> Result<Value, RpcError> {
+    let client = new_aw_client(cfg);
+    let info = client.get_info()?;
+    let buckets = client.get_buckets()?;
+    let host_buckets = info
+        .get("hostname")
+        .and_then(|v| v.as_str())
+        .map(|hostname| HostBuckets::from_buckets(hostname, &buckets));
+    let mut activity = host_buckets
+        .as_ref()
+        .and_then(|hb| client.current_activity_for(hb).ok());
+    if let Some(activity) = activity.as_mut() {
+        new_redactor(cfg)?.activity(activity);
+    }
     Ok(json!({
         "info": info,
         "buckets": buckets,
+        "host_buckets": host_buckets,
+        "activity": activity,
     }))
 }
 
-fn nowframe_build(cfg: &Config, params: Value) -> Result<Value, String> {
+fn nowframe_build(cfg: &Config, params: Value) -> Result<Value, RpcError> {
     let reason = params
         .get("reason")
         .and_then(|v| v.as_str())
         .unwrap_or("mcp")
         .to_string();
+    let explain = params
+        .get("explain")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
 
-    let aw_client = AwClient::new(cfg.aw.base_url.clone());
-    let omni_client = OmniClient::new(cfg.omni.base_url.clone());
+    let aw_client = new_aw_client(cfg);
+    let omni_client = new_omni_client(cfg);
+    let activity = aw_client.current_activity().ok();
+
+    let trigger = if explain {
+        let mut engine = TriggerEngine::from_path(cfg.trigger.rules_path.as_deref().map(Path::new))
+            .map_err(|e| RpcError::from(e.to_string()))?
+            .with_gate(GateConfig {
+                fire_above: cfg.watch.threshold,
+                ..GateConfig::default()
+            })
+            .with_state(watch_trigger_state(cfg));
+        // Compare against what the watcher saw last, with its rule cooldowns, so
+        // `first_observation` only counts when nothing has been observed yet.
+        // The persisted title is redacted; redact ours the same way before comparing.
+        let mut current = activity.as_ref().map(activity_signal).unwrap_or_default();
+        let app = current.app.clone();
+        new_redactor(cfg)?.option(&mut current.title, app.as_deref(), Field::Title);
+        let mut input = TriggerInput::manual(&reason, current);
+        input.previous = engine.state().last_signal.clone();
+        input.dwell_secs = activity
+            .as_ref()
+            .map(|a| a.window_duration_secs)
+            .unwrap_or(0.0);
+        Some(engine.explain(&input))
+    } else {
+        None
+    };
 
-    let nowframe = NowFrame {
-        timestamp: Utc::now().to_rfc3339(),
-        reason,
-        aw_info: aw_client.get_info().ok(),
-        aw_buckets: aw_client.get_buckets().ok(),
+    let mut nowframe = NowFrame {
+        activity,
         omni_probe: omni_client.probe().ok(),
+        trigger,
+        screen: ScreenSummary::from_latest(
+            Path::new(&cfg.paths.cache_screens),
+            ScreenSummary::DEFAULT_MAX_AGE,
+            Utc::now(),
+        ),
+        ..NowFrame::new(reason)
     };
+    nowframe.redact(&new_redactor(cfg)?);
 
-    serde_json::to_value(&nowframe).map_err(|e| e.to_string())
+    serde_json::to_value(&nowframe).map_err(|e| RpcError::from(e.to_string()))
 }
 
-fn system_health(cfg: &Config) -> Result<Value, String> {
-    let aw_client = AwClient::new(cfg.aw.base_url.clone());
-    let omni_client = OmniClient::new(cfg.omni.base_url.clone());
+/// The daemon watcher's gate state, read-only; a missing or unreadable file is a fresh state.
+fn watch_trigger_state(cfg: &Config) -> TriggerState {
+    let path = match cfg.trigger.state_path.as_ref() {
+        Some(path) => PathBuf::from(path),
+        None => Path::new(&cfg.paths.root)
+            .join("runtime")
+            .join("trigger_state.json"),
+    };
+    fs::read_to_string(&path)
+        .ok()
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+fn new_redactor(cfg: &Config) -> Result<Redactor, RpcError> {
+    Redactor::new(&cfg.redaction)
+        .map_err(|e| RpcError::from(format!("invalid [redaction] config: {}", e)))
+}
+
+fn cache_manager(cfg: &Config) -> CacheManager {
+    CacheManager::new(
+        &cfg.paths.cache_screens,
+        &cfg.paths.cache_thumbs,
+        &cfg.paths.cache_tmp,
+        &cfg.cache,
+    )
+}
+
+fn nowframe_store(cfg: &Config) -> NowFrameStore {
+    // Read-only here; the daemon applies retention when it writes.
+    NowFrameStore::new(&cfg.paths.data_nowframes, RetentionPolicy::default())
+}
+
+fn nowframe_list(cfg: &Config, params: Value) -> Result<Value, RpcError> {
+    let time_param = |key: &str| -> Result<Option<chrono::DateTime<Utc>>, RpcError> {
+        match params.get(key).and_then(|v| v.as_str()) {
+            Some(text) => chrono::DateTime::parse_from_rfc3339(text)
+                .map(|t| Some(t.with_timezone(&Utc)))
+                .map_err(|e| RpcError::invalid_request(format!("{}: {}", key, e))),
+            None => Ok(None),
+        }
+    };
+    let query = ListQuery {
+        since: time_param("since")?,
+        until: time_param("until")?,
+        limit: Some(params.get("limit").and_then(|v| v.as_u64()).unwrap_or(50) as usize),
+    };
+    let frames = nowframe_store(cfg).list(&query)?;
+    Ok(json!({ "frames": frames }))
+}
+
+fn nowframe_get(cfg: &Config, params: Value) -> Result<Value, RpcError> {
+    let id = params.get("id").and_then(|v| v.as_str()).unwrap_or("latest");
+    let (id, frame) = load_nowframe(cfg, id)?;
+    Ok(json!({ "id": id, "frame": frame }))
+}
+
+/// A stored NowFrame by id, or the newest for `latest`, redacted for output.
+fn load_nowframe(cfg: &Config, id: &str) -> Result<(String, NowFrame), RpcError> {
+    let store = nowframe_store(cfg);
+    let (id, mut frame) = if id == "latest" {
+        match store.latest()? {
+            Some((entry, frame)) => (entry.id, frame),
+            None => return Err(StoreError::NotFound("latest".to_string()).into()),
+        }
+    } else {
+        (id.to_string(), store.get(id)?)
+    };
+    frame.redact(&new_redactor(cfg)?);
+    Ok((id, frame))
+}
+
+fn episodes_list(cfg: &Config, params: Value) -> Result<Value, RpcError> {
+    if !cfg.episodes.enabled {
+        return Err(RpcError::consent_required(
+            "episodes are disabled; set [episodes] enabled = true to opt in",
+        ));
+    }
+    let hours = match params.get("hours") {
+        None | Some(Value::Null) => 24,
+        Some(value) => value
+            .as_i64()
+            .filter(|h| (1..=MAX_EPISODE_HOURS).contains(h))
+            .ok_or_else(|| {
+                RpcError::invalid_request(format!(
+                    "hours must be an integer between 1 and {}: {}",
+                    MAX_EPISODE_HOURS, value
+                ))
+            })?,
+    };
+    let redactor = new_redactor(cfg)?;
+    let store = nowframe_store(cfg);
+    let entries = store.list(&ListQuery {
+        since: Some(Utc::now() - chrono::Duration::hours(hours)),
+        ..ListQuery::default()
+    })?;
+    let frames = entries
+        .iter()
+        .rev()
+        .filter_map(|entry| store.get(&entry.id).ok())
+        .map(|mut frame| {
+            frame.redact(&redactor);
+            frame
+        })
+        .collect::<Vec<_>>();
+    let episodes = segment(&frames, &cfg.episodes.segmenter);
+    Ok(json!({ "frames": frames.len(), "episodes": episodes }))
+}
+
+fn system_health(cfg: &Config) -> Result<Value, RpcError> {
+    let aw_client = new_aw_client(cfg);
+    let omni_client = new_omni_client(cfg);
 
     let aw_ok = aw_client.get_info().is_ok();
-    let omni_probe = omni_client.probe().ok();
-    let sidecar_ready = omni_probe
-        .as_ref()
-        .and_then(|v| v.get("ok"))
-        .and_then(|v| v.as_bool())
-        .unwrap_or(false);
-    let preflight_ok = omni_probe
+    let sidecar = omni_client.status();
+    let sidecar_ready = sidecar.as_ref().map(|s| s.ready).unwrap_or(false);
+    let preflight_ok = sidecar
         .as_ref()
-        .and_then(|v| v.get("preflight_ok"))
-        .and_then(|v| v.as_bool())
+        .ok()
+        .and_then(|s| s.preflight_ok)
         .unwrap_or(sidecar_ready);
+    let sidecar_reason = match &sidecar {
+        Ok(status) => status.reason.as_str(),
+        Err(err) => err.kind(),
+    };
 
     let (protected_env_ok, protected_diff_count) = protected_env_status(cfg);
 
     Ok(json!({
         "aw_ok": aw_ok,
         "sidecar_probe_status": sidecar_ready,
+        "sidecar_reason": sidecar_reason,
         "preflight_ok": preflight_ok,
         "protected_env_ok": protected_env_ok,
         "protected_diff_count": protected_diff_count,
-        "omni_probe": omni_probe,
+        "sidecar_status": sidecar.as_ref().ok(),
+        "sidecar_error": sidecar.as_ref().err().map(|e| e.to_string()),
     }))
 }
 
-fn screen_capture(cfg: &Config, params: Value) -> Result<Value, String> {
-    let mode = params
-        .get("mode")
-        .and_then(|v| v.as_str())
-        .unwrap_or("full");
-    let format = params
-        .get("format")
-        .and_then(|v| v.as_str())
-        .unwrap_or("png");
-    let with_cursor = params
-        .get("with_cursor")
-        .and_then(|v| v.as_bool())
-        .unwrap_or(false);
-
-    let capture = capture_screen_internal(cfg, mode, format, with_cursor)?;
+fn screen_capture(cfg: &Config, params: Value) -> Result<Value, RpcError> {
+    let (mode, with_cursor) = capture_params(&params)?;
+
+    let strict = new_redactor(cfg)?.strict_screenshots();
+    let capture = capture_screen_internal(cfg, mode, with_cursor, strict)?;
+    // Strict mode cannot save anything before the sensitive regions are known.
+    let parse = match capture.pending.as_ref() {
+        Some(image) => Some(parse_screen_internal(
+            cfg,
+            Some(capture.frame_id.clone()),
+            &capture.raw_path,
+            Some(image),
+            params.get("parse_options").cloned(),
+            wait_ready_param(&params)?,
+        )?),
+        None => None,
+    };
 
     Ok(json!({
         "frame_id": capture.frame_id,
@@ -650,14 +1034,16 @@ fn screen_capture(cfg: &Config, params: Value) -> Result<Value, String> {
         "width": capture.width,
         "height": capture.height,
         "cursor_included": capture.cursor_included,
+        "redacted_path": parse.as_ref().and_then(|p| p.redacted_path.as_ref()).map(|p| path_to_string(p)),
     }))
 }
 
-fn screen_parse(cfg: &Config, params: Value) -> Result<Value, String> {
+fn screen_parse(cfg: &Config, params: Value) -> Result<Value, RpcError> {
     let parse_options = params.get("parse_options").cloned();
+    let wait_ready = wait_ready_param(&params)?;
     let (frame_id, raw_path) = resolve_frame_input(cfg, &params)?;
 
-    let parse = parse_screen_internal(cfg, frame_id, &raw_path, parse_options)?;
+    let parse = parse_screen_internal(cfg, frame_id, &raw_path, None, parse_options, wait_ready)?;
     let json_path = write_parse_json(cfg, &parse)?;
 
     Ok(json!({
@@ -668,33 +1054,32 @@ fn screen_parse(cfg: &Config, params: Value) -> Result<Value, String> {
         "has_text": parse.has_text,
         "has_icon": parse.has_icon,
         "som_path": parse.som_path.as_ref().map(|p| path_to_string(p)),
+        "redacted_path": parse.redacted_path.as_ref().map(|p| path_to_string(p)),
+        "redacted_regions": parse.redacted_regions,
         "json_path": path_to_string(&json_path),
     }))
 }
 
-fn screen_bundle(cfg: &Config, params: Value) -> Result<Value, String> {
-    let mode = params
-        .get("mode")
-        .and_then(|v| v.as_str())
-        .unwrap_or("full");
-    let format = params
-        .get("format")
-        .and_then(|v| v.as_str())
-        .unwrap_or("png");
-    let with_cursor = params
-        .get("with_cursor")
-        .and_then(|v| v.as_bool())
-        .unwrap_or(false);
-    let include_b64 = params
-        .get("include_b64")
-        .and_then(|v| v.as_bool())
-        .unwrap_or(false);
+fn screen_bundle(cfg: &Config, params: Value) -> Result<Value, RpcError> {
+    let (mode, with_cursor) = capture_params(&params)?;
+    let include_b64 = bool_param(&params, "include_b64")?;
     let parse_options = params.get("parse_options").cloned();
+    let wait_ready = wait_ready_param(&params)?;
 
-    let capture = capture_screen_internal(cfg, mode, format, with_cursor)?;
-    let parse = parse_screen_internal(cfg, Some(capture.frame_id.clone()), &capture.raw_path, parse_options)?;
+    let strict = new_redactor(cfg)?.strict_screenshots();
+    let capture = capture_screen_internal(cfg, mode, with_cursor, strict)?;
+    let parse = parse_screen_internal(
+        cfg,
+        Some(capture.frame_id.clone()),
+        &capture.raw_path,
+        capture.pending.as_ref(),
+        parse_options,
+        wait_ready,
+    )?;
+    // Everything derived from the screenshot starts from the redacted variant.
+    let shown_path = parse.redacted_path.clone().unwrap_or_else(|| capture.raw_path.clone());
 
-    let (annotated_path, mask_path) = build_annotations(cfg, &capture.raw_path, capture.width, capture.height, &parse.elements, &capture.frame_id)?;
+    let (annotated_path, mask_path) = build_annotations(cfg, &shown_path, capture.width, capture.height, &parse.elements, &capture.frame_id)?;
 
     let aw_context = aw_context_json(cfg);
     let bundle_json = json!({
@@ -708,6 +1093,8 @@ fn screen_bundle(cfg: &Config, params: Value) -> Result<Value, String> {
         "has_text": parse.has_text,
         "has_icon": parse.has_icon,
         "som_path": parse.som_path.as_ref().map(|p| path_to_string(p)),
+        "redacted_path": parse.redacted_path.as_ref().map(|p| path_to_string(p)),
+        "redacted_regions": parse.redacted_regions,
         "aw_context": aw_context,
     });
 
@@ -723,21 +1110,31 @@ fn screen_bundle(cfg: &Config, params: Value) -> Result<Value, String> {
             mask_path: Some(path_to_string(&mask_path)),
             json_path: Some(path_to_string(&json_path)),
             som_path: parse.som_path.as_ref().map(|p| path_to_string(p)),
+            redacted_path: parse.redacted_path.as_ref().map(|p| path_to_string(p)),
         },
     )?;
 
+    // Runs after latest.json names the new frame, so the frame just written is never evicted.
+    if let Err(err) = cache_manager(cfg).gc(Utc::now(), false) {
+        log_line(&format!("cache_gc_error={}", err));
+    }
+
+    if let Err(err) = emit_frame_heartbeat(cfg, &capture.frame_id, &parse) {
+        log_line(&format!("aw_heartbeat_error={}", err));
+    }
+
     if include_b64 {
-        let raw_b64 = match encode_base64_with_limit(&capture.raw_path) {
+        let raw_b64 = match encode_base64_with_limit(&shown_path) {
             Ok(value) => value,
-            Err(err) => return Err(format!("missing_b64: raw_b64 ({})", err)),
+            Err(err) => return Err(format!("missing_b64: raw_b64 ({})", err).into()),
         };
         let annotated_b64 = match encode_base64_with_limit(&annotated_path) {
             Ok(value) => value,
-            Err(err) => return Err(format!("missing_b64: annotated_b64 ({})", err)),
+            Err(err) => return Err(format!("missing_b64: annotated_b64 ({})", err).into()),
         };
         let mask_b64 = match encode_base64_with_limit(&mask_path) {
             Ok(value) => value,
-            Err(err) => return Err(format!("missing_b64: mask_b64 ({})", err)),
+            Err(err) => return Err(format!("missing_b64: mask_b64 ({})", err).into()),
         };
         let mut response_json = bundle_json.clone();
         if let Value::Object(map) = &mut response_json {
@@ -778,45 +1175,40 @@ fn screen_bundle(cfg: &Config, params: Value) -> Result<Value, String> {
     }
 }
 
-fn resource_read(cfg: &Config, params: Value) -> Result<Value, String> {
-    let uri = params
-        .get("uri")
-        .and_then(|v| v.as_str())
-        .ok_or_else(|| "missing uri".to_string())?;
-
-    let latest = load_latest_bundle(cfg).ok_or_else(|| "latest bundle not found".to_string())?;
-
-    let path = match uri {
-        "screen://latest/raw" => latest.raw_path,
-        "screen://latest/annotated" => latest
-            .annotated_path
-            .ok_or_else(|| "annotated image not available".to_string())?,
-        "screen://latest/mask" => latest
-            .mask_path
-            .ok_or_else(|| "mask image not available".to_string())?,
-        _ => return Err(format!("unknown resource uri: {}", uri)),
+/// Each frame is its own event in the omni bucket: `frame_id` differs per capture,
+/// so AW never merges two frames.
+fn emit_frame_heartbeat(cfg: &Config, frame_id: &str, parse: &ParseMeta) -> Result<(), String> {
+    let hb = match cfg.aw_heartbeat.as_ref() {
+        Some(hb) if hb.enabled => hb,
+        _ => return Ok(()),
     };
 
-    let blob = encode_base64_with_limit(Path::new(&path))?;
-    Ok(json!({
-        "contents": [{
-            "uri": uri,
-            "mimeType": "image/png",
-            "blob": blob
-        }]
-    }))
+    let summary = ScreenSummary::from_elements(frame_id, Utc::now(), &parse.elements);
+    let mut data = Map::new();
+    data.insert("source".to_string(), json!("mcp"));
+    data.insert("frame_id".to_string(), json!(summary.frame_id));
+    data.insert("element_count".to_string(), json!(summary.element_count));
+    data.insert("text_count".to_string(), json!(summary.text_count));
+    data.insert("icon_count".to_string(), json!(summary.icon_count));
+    data.insert("dominant_text".to_string(), json!(summary.dominant_text));
+    data.insert("latency_ms".to_string(), json!(parse.latency_ms));
+
+    let mut event = Event::new(Utc::now(), data);
+    new_redactor(cfg).map_err(|e| e.message)?.event(&mut event);
+
+    let client = new_aw_client(cfg);
+    let bucket_id = client.ensure_omni_bucket().map_err(|e| e.to_string())?;
+    client
+        .heartbeat(&bucket_id, &event, hb.pulsetime_secs)
+        .map_err(|e| e.to_string())
 }
 
 fn capture_screen_internal(
     cfg: &Config,
     mode: &str,
-    format: &str,
     with_cursor: bool,
+    strict: bool,
 ) -> Result<CaptureMeta, String> {
-    if format != "png" {
-        return Err("format_not_supported".to_string());
-    }
-
     let cache_dir = PathBuf::from(&cfg.paths.cache_screens);
     fs::create_dir_all(&cache_dir)
         .map_err(|e| format!("create cache dir failed: {}", e))?;
@@ -830,7 +1222,12 @@ fn capture_screen_internal(
     let width = image.width();
     let height = image.height();
 
-    save_rgba_image(&image, &raw_path)?;
+    let pending = if strict {
+        Some(image)
+    } else {
+        save_rgba_image(&image, &raw_path)?;
+        None
+    };
 
     Ok(CaptureMeta {
         frame_id,
@@ -839,6 +1236,7 @@ fn capture_screen_internal(
         width,
         height,
         cursor_included,
+        pending,
     })
 }
 
@@ -846,39 +1244,88 @@ fn parse_screen_internal(
     cfg: &Config,
     frame_id: Option<String>,
     raw_path: &Path,
+    pending: Option<&RgbaImage>,
     parse_options: Option<Value>,
-) -> Result<ParseMeta, String> {
+    wait_ready: Duration,
+) -> Result<ParseMeta, RpcError> {
     let cache_dir = PathBuf::from(&cfg.paths.cache_screens);
     fs::create_dir_all(&cache_dir)
         .map_err(|e| format!("create cache dir failed: {}", e))?;
 
     let frame_id = frame_id.unwrap_or_else(new_frame_id);
-    let bytes = fs::read(raw_path).map_err(|e| format!("read image failed: {}", e))?;
+    let (bytes, width, height) = match pending {
+        Some(image) => (encode_png(image)?, image.width(), image.height()),
+        None => {
+            let bytes = fs::read(raw_path).map_err(|e| format!("read image failed: {}", e))?;
+            let (width, height) = image::image_dimensions(raw_path)
+                .map_err(|e| format!("read image size failed: {}", e))?;
+            (bytes, width, height)
+        }
+    };
     let encoded = BASE64_ENGINE.encode(bytes);
 
-    let omni_client = OmniClient::new(cfg.omni.base_url.clone());
-    let response = omni_client
-        .parse(&encoded, parse_options.as_ref())
-        .map_err(|e| e.to_string())?;
-
-    let latency_ms = response
-        .get("latency_ms")
-        .and_then(|v| v.as_i64())
-        .unwrap_or(0);
-
-    let elements_value = response
-        .get("parsed_content_list")
-        .cloned()
-        .unwrap_or_else(|| Value::Array(vec![]));
-    let elements = elements_value.as_array().cloned().unwrap_or_default();
-
-    let has_text = elements.iter().any(|el| element_has_kind(el, "text"));
-    let has_icon = elements.iter().any(|el| element_has_kind(el, "icon"));
+    let omni_client = new_omni_client(cfg);
+    let mut response =
+        omni_client.parse_when_ready(&encoded, parse_options.as_ref(), wait_ready)?;
+    response.resolve_bboxes(width, height);
+
+    // Redact before anything is written: the parse JSON, bundle JSON and heartbeat
+    // all reuse these elements.
+    let redactor = new_redactor(cfg)?;
+    let app = if redactor.has_app_rules() {
+        new_aw_client(cfg)
+            .current_activity()
+            .ok()
+            .and_then(|activity| activity.app)
+    } else {
+        None
+    };
+    // Regions are located on the text as parsed, before it is rewritten below.
+    let mode = redactor.pixel_mode();
+    let regions = match mode {
+        PixelMode::Off => Vec::new(),
+        _ => redactor.sensitive_regions(&response.elements, app.as_deref()),
+    };
+    redactor.elements(&mut response.elements, app.as_deref());
+
+    let redacted_path = match mode {
+        PixelMode::Off => None,
+        _ => {
+            let mut image = match pending {
+                Some(image) => image.clone(),
+                None => image::open(raw_path)
+                    .map_err(|e| format!("open image failed: {}", e))?
+                    .to_rgba8(),
+            };
+            redact_regions(&mut image, &regions, mode, redactor.padding_px());
+            // A pending capture has never been written, so its redacted pixels
+            // become the raw frame; otherwise the original stays alongside.
+            let path = match pending {
+                Some(_) => raw_path.to_path_buf(),
+                None => cache_dir.join(format!("{}_redacted.png", frame_id)),
+            };
+            save_rgba_image(&image, &path)?;
+            Some(path)
+        }
+    };
 
     let som_path = response
-        .get("som_image_base64")
-        .and_then(|v| v.as_str())
+        .som_image_base64
+        .as_deref()
         .and_then(|s| decode_base64_image(s).ok())
+        .and_then(|bytes| match mode {
+            PixelMode::Off => Some(bytes),
+            // The SoM overlay is drawn on the original pixels; redact it the same
+            // way, or drop it when its geometry does not match the capture.
+            _ => {
+                let mut som = image::load_from_memory(&bytes).ok()?.to_rgba8();
+                if som.dimensions() != (width, height) {
+                    return None;
+                }
+                redact_regions(&mut som, &regions, mode, redactor.padding_px());
+                encode_png(&som).ok()
+            }
+        })
         .and_then(|bytes| {
             let som_path = cache_dir.join(format!("{}_som.png", frame_id));
             if fs::write(&som_path, bytes).is_ok() {
@@ -891,21 +1338,66 @@ fn parse_screen_internal(
     Ok(ParseMeta {
         frame_id,
         raw_path: raw_path.to_path_buf(),
-        elements,
-        latency_ms,
-        has_text,
-        has_icon,
+        has_text: response.has_text(),
+        has_icon: response.has_icon(),
+        latency_ms: response.latency_ms,
+        elements: response.elements,
         som_path,
-        response,
+        redacted_path,
+        redacted_regions: regions.len(),
     })
 }
 
+/// `region` grown by `padding` and clipped to a `width` x `height` image, as
+/// `(x, y, width, height)`; `None` when nothing of it is inside. Boxes come from the
+/// sidecar, so every sum saturates instead of trusting the coordinates.
+fn clip_region(
+    region: &PixelBox,
+    padding: u32,
+    width: u32,
+    height: u32,
+) -> Option<(u32, u32, u32, u32)> {
+    let x = region.x.saturating_sub(padding);
+    let y = region.y.saturating_sub(padding);
+    let right = region.x.saturating_add(region.width).saturating_add(padding);
+    let bottom = region.y.saturating_add(region.height).saturating_add(padding);
+    let (right, bottom) = (right.min(width), bottom.min(height));
+    if right <= x || bottom <= y {
+        return None;
+    }
+    Some((x, y, right - x, bottom - y))
+}
+
+/// Paints over `regions` (grown by `padding` pixels) in place.
+fn redact_regions(image: &mut RgbaImage, regions: &[PixelBox], mode: PixelMode, padding: u32) {
+    for region in regions {
+        let (image_width, image_height) = image.dimensions();
+        let Some((x, y, width, height)) = clip_region(region, padding, image_width, image_height)
+        else {
+            continue;
+        };
+        match mode {
+            PixelMode::Off => {}
+            PixelMode::Blackout => {
+                let rect = Rect::at(x as i32, y as i32).of_size(width, height);
+                draw_filled_rect_mut(image, rect, Rgba([0, 0, 0, 255]));
+            }
+            PixelMode::Blur => {
+                // Sigma scales with the box so glyphs of any size are smeared beyond recognition.
+                let sigma = (width.min(height) as f32 / 2.0).max(8.0);
+                let blurred = image::imageops::blur(&*image.view(x, y, width, height), sigma);
+                image::imageops::replace(image, &blurred, x as i64, y as i64);
+            }
+        }
+    }
+}
+
 fn build_annotations(
     cfg: &Config,
     raw_path: &Path,
     width: u32,
     height: u32,
-    elements: &[Value],
+    elements: &[ParsedElement],
     frame_id: &str,
 ) -> Result<(PathBuf, PathBuf), String> {
     let cache_dir = PathBuf::from(&cfg.paths.cache_screens);
@@ -918,8 +1410,8 @@ fn build_annotations(
     let mut mask = RgbaImage::from_pixel(width, height, Rgba([0, 0, 0, 255]));
 
     for el in elements {
-        let rect = match extract_bbox(el, width, height) {
-            Some(rect) => rect,
+        let rect = match el.bbox_px.and_then(|px| clip_region(&px, 0, width, height)) {
+            Some((x, y, w, h)) => Rect::at(x as i32, y as i32).of_size(w, h),
             None => continue,
         };
         let color = element_color(el);
@@ -950,7 +1442,6 @@ fn write_parse_json(cfg: &Config, parse: &ParseMeta) -> Result<PathBuf, String>
         "has_icon": parse.has_icon,
         "elements": parse.elements,
         "som_path": parse.som_path.as_ref().map(|p| path_to_string(p)),
-        "response": parse.response,
     });
 
     let text = serde_json::to_string_pretty(&payload)
@@ -983,39 +1474,115 @@ fn update_latest_bundle(cfg: &Config, latest: LatestBundle) -> Result<(), String
 
     let lock = LATEST_BUNDLE.get_or_init(|| Mutex::new(None));
     let mut guard = lock.lock().map_err(|_| "latest lock poisoned".to_string())?;
+    let frame_id = latest.frame_id.clone();
     *guard = Some(latest);
+    drop(guard);
+    notify::latest_bundle_changed(&frame_id);
     Ok(())
 }
 
 fn load_latest_bundle(cfg: &Config) -> Option<LatestBundle> {
-    let lock = LATEST_BUNDLE.get_or_init(|| Mutex::new(None));
-    if let Ok(guard) = lock.lock() {
-        if let Some(value) = guard.clone() {
-            return Some(value);
-        }
+    // The file wins: another process may have moved latest.json on since this one
+    // last wrote it, and subscribers have already been told so.
+    let path = latest_path(cfg);
+    if let Some(latest) = fs::read_to_string(path)
+        .ok()
+        .and_then(|text| serde_json::from_str(&text).ok())
+    {
+        return Some(latest);
     }
 
-    let path = latest_path(cfg);
-    let text = fs::read_to_string(path).ok()?;
-    serde_json::from_str(&text).ok()
+    let lock = LATEST_BUNDLE.get_or_init(|| Mutex::new(None));
+    lock.lock().ok().and_then(|guard| guard.clone())
 }
 
 fn latest_path(cfg: &Config) -> PathBuf {
     PathBuf::from(&cfg.paths.cache_screens).join("latest.json")
 }
 
-fn resolve_frame_input(cfg: &Config, params: &Value) -> Result<(Option<String>, PathBuf), String> {
-    if let Some(raw_path) = params.get("raw_path").and_then(|v| v.as_str()) {
+/// `mode` and `with_cursor` of a capture tool; `format` only accepts `png`.
+fn capture_params(params: &Value) -> Result<(&str, bool), RpcError> {
+    let mode = str_param(params, "mode")?.unwrap_or("full");
+    if !matches!(mode, "full" | "active") {
+        return Err(RpcError::invalid_request(format!(
+            "mode must be full or active: {}",
+            mode
+        )));
+    }
+    let format = str_param(params, "format")?.unwrap_or("png");
+    if format != "png" {
+        return Err(RpcError::invalid_request(format!(
+            "format_not_supported: {}",
+            format
+        )));
+    }
+    Ok((mode, bool_param(params, "with_cursor")?))
+}
+
+fn str_param<'a>(params: &'a Value, key: &str) -> Result<Option<&'a str>, RpcError> {
+    match params.get(key) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(text)) => Ok(Some(text)),
+        Some(value) => Err(RpcError::invalid_request(format!(
+            "{} must be a string: {}",
+            key, value
+        ))),
+    }
+}
+
+fn bool_param(params: &Value, key: &str) -> Result<bool, RpcError> {
+    match params.get(key) {
+        None | Some(Value::Null) => Ok(false),
+        Some(value) => value.as_bool().ok_or_else(|| {
+            RpcError::invalid_request(format!("{} must be a boolean: {}", key, value))
+        }),
+    }
+}
+
+fn wait_ready_param(params: &Value) -> Result<Duration, RpcError> {
+    match params.get("wait_ready_ms") {
+        None | Some(Value::Null) => Ok(Duration::ZERO),
+        Some(value) => value.as_u64().map(Duration::from_millis).ok_or_else(|| {
+            RpcError::invalid_request(format!(
+                "wait_ready_ms must be a non-negative integer: {}",
+                value
+            ))
+        }),
+    }
+}
+
+fn resolve_frame_input(
+    cfg: &Config,
+    params: &Value,
+) -> Result<(Option<String>, PathBuf), RpcError> {
+    if let Some(raw_path) = str_param(params, "raw_path")? {
         return Ok((None, PathBuf::from(raw_path)));
     }
 
-    if let Some(frame_id) = params.get("frame_id").and_then(|v| v.as_str()) {
+    if let Some(frame_id) = str_param(params, "frame_id")? {
+        check_frame_id(frame_id)?;
         let raw_path = PathBuf::from(&cfg.paths.cache_screens)
             .join(format!("{}_raw.png", frame_id));
         return Ok((Some(frame_id.to_string()), raw_path));
     }
 
-    Err("missing frame_id or raw_path".to_string())
+    Err(RpcError::invalid_request("missing frame_id or raw_path"))
+}
+
+/// Frame ids become file names; refuse anything that could leave cache_screens.
+fn check_frame_id(frame_id: &str) -> Result<(), RpcError> {
+    let valid = !frame_id.is_empty()
+        && frame_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if valid {
+        Ok(())
+    } else {
+        Err(RpcError::invalid_request(format!(
+            "invalid frame id: {}",
+            frame_id
+        )))
+    }
 }
 
 fn new_frame_id() -> String {
@@ -1053,6 +1620,14 @@ fn encode_base64_with_limit(path: &Path) -> Result<String, String> {
     Ok(b64)
 }
 
+fn encode_png(image: &RgbaImage) -> Result<Vec<u8>, String> {
+    let mut bytes = Cursor::new(Vec::new());
+    image
+        .write_to(&mut bytes, ImageFormat::Png)
+        .map_err(|e| format!("encode image failed: {}", e))?;
+    Ok(bytes.into_inner())
+}
+
 fn save_rgba_image(image: &RgbaImage, path: &Path) -> Result<(), String> {
     let dyn_img = DynamicImage::ImageRgba8(image.clone());
     dyn_img
@@ -1060,83 +1635,36 @@ fn save_rgba_image(image: &RgbaImage, path: &Path) -> Result<(), String> {
         .map_err(|e| format!("save image failed: {}", e))
 }
 
-fn element_has_kind(el: &Value, kind: &str) -> bool {
-    if let Some(value) = el.get("type").and_then(|v| v.as_str()) {
-        if value.eq_ignore_ascii_case(kind) {
-            return true;
-        }
-    }
-    if let Some(value) = el.get("category").and_then(|v| v.as_str()) {
-        if value.to_lowercase().contains(kind) {
-            return true;
-        }
-    }
-    if kind == "text" {
-        if let Some(value) = el.get("content").and_then(|v| v.as_str()) {
-            if !value.trim().is_empty() {
-                return true;
-            }
-        }
-    }
-    false
-}
-
-fn element_color(el: &Value) -> Rgba<u8> {
-    if element_has_kind(el, "text") {
-        Rgba([0, 200, 0, 255])
-    } else if element_has_kind(el, "icon") {
-        Rgba([200, 0, 0, 255])
-    } else {
-        Rgba([200, 200, 0, 255])
+fn element_color(el: &ParsedElement) -> Rgba<u8> {
+    match el.kind {
+        ElementKind::Text => Rgba([0, 200, 0, 255]),
+        ElementKind::Icon => Rgba([200, 0, 0, 255]),
+        ElementKind::Other => Rgba([200, 200, 0, 255]),
     }
 }
 
-fn extract_bbox(el: &Value, width: u32, height: u32) -> Option<Rect> {
-    let bbox = el.get("bbox")?.as_array()?;
-    if bbox.len() < 4 {
-        return None;
-    }
-
-    let mut vals = [0.0f32; 4];
-    for (idx, val) in bbox.iter().take(4).enumerate() {
-        vals[idx] = val.as_f64().unwrap_or(0.0) as f32;
-    }
-
-    let max_val = vals.iter().cloned().fold(0.0_f32, f32::max);
-    let normalized = max_val <= 1.5;
-
-    let mut x1 = vals[0];
-    let mut y1 = vals[1];
-    let mut x2 = vals[2];
-    let mut y2 = vals[3];
-
-    if normalized {
-        x1 *= width as f32;
-        x2 *= width as f32;
-        y1 *= height as f32;
-        y2 *= height as f32;
-    }
-
-    let x1 = x1.round().clamp(0.0, width.saturating_sub(1) as f32) as i32;
-    let y1 = y1.round().clamp(0.0, height.saturating_sub(1) as f32) as i32;
-    let x2 = x2.round().clamp(0.0, width as f32) as i32;
-    let y2 = y2.round().clamp(0.0, height as f32) as i32;
-
-    let w = (x2 - x1).max(1) as u32;
-    let h = (y2 - y1).max(1) as u32;
-
-    Some(Rect::at(x1, y1).of_size(w, h))
-}
-
 fn path_to_string(path: &Path) -> String {
     path.to_string_lossy().to_string()
 }
 
 fn aw_context_json(cfg: &Config) -> Value {
-    let client = AwClient::new(cfg.aw.base_url.clone());
+    let client = new_aw_client(cfg);
+    let host_buckets = client.discover_buckets().ok();
+    // Without a valid redactor the activity is withheld rather than sent raw.
+    let activity = match new_redactor(cfg) {
+        Ok(redactor) => host_buckets
+            .as_ref()
+            .and_then(|buckets| client.current_activity_for(buckets).ok())
+            .map(|mut activity| {
+                redactor.activity(&mut activity);
+                activity
+            }),
+        Err(_) => None,
+    };
     json!({
         "info": client.get_info().ok(),
-        "buckets": client.get_buckets().ok(),
+        "host_buckets": host_buckets,
+        "activity": activity,
     })
 }
 
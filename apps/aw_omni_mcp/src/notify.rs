@@ -0,0 +1,242 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::Duration;
+
+use nowframe_core::{ListQuery, NowFrameStore, RetentionPolicy};
+use serde_json::{json, Value};
+
+use crate::{log_line, RpcError};
+
+/// How often the watcher looks at `latest.json` and the NowFrame store for
+/// changes made by other processes (the daemon, another MCP instance).
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+const LATEST_URIS: &[&str] = &[
+    "screen://latest/raw",
+    "screen://latest/annotated",
+    "screen://latest/mask",
+    "screen://latest/som",
+    "screen://latest/json",
+];
+const NOWFRAME_LATEST: &str = "nowframe://latest";
+/// The one session of the stdio transport; HTTP sessions use `Mcp-Session-Id`.
+pub const STDIO_SESSION: &str = "stdio";
+
+/// Receives the server-initiated messages of one session; each open stream of
+/// that session registers one.
+pub type Sink = Arc<dyn Fn(&Value) + Send + Sync>;
+
+/// Subscribed URIs per session.
+static SUBSCRIPTIONS: OnceLock<Mutex<BTreeMap<String, BTreeSet<String>>>> = OnceLock::new();
+static SINKS: OnceLock<Mutex<Vec<(u64, String, Sink)>>> = OnceLock::new();
+static NEXT_SINK: AtomicU64 = AtomicU64::new(1);
+/// Last seen latest frame id and NowFrame id, so a change is announced once
+/// whether it was noticed in-process or by the watcher.
+static SEEN: OnceLock<Mutex<(Option<String>, Option<String>)>> = OnceLock::new();
+static WATCHED: OnceLock<Watched> = OnceLock::new();
+
+/// Where the watcher looks for changes made by other processes.
+struct Watched {
+    latest_path: PathBuf,
+    store: NowFrameStore,
+}
+
+impl Watched {
+    fn latest_frame_id(&self) -> Option<String> {
+        let text = fs::read_to_string(&self.latest_path).ok()?;
+        let value: Value = serde_json::from_str(&text).ok()?;
+        value.get("frame_id")?.as_str().map(str::to_string)
+    }
+
+    fn newest_nowframe_id(&self) -> Option<String> {
+        let query = ListQuery {
+            limit: Some(1),
+            ..ListQuery::default()
+        };
+        self.store
+            .list(&query)
+            .ok()?
+            .into_iter()
+            .next()
+            .map(|e| e.id)
+    }
+}
+
+pub fn add_sink(session: &str, sink: Sink) -> u64 {
+    let id = NEXT_SINK.fetch_add(1, Ordering::Relaxed);
+    sinks()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .push((id, session.to_string(), sink));
+    id
+}
+
+/// Drops a sink whose transport went away (a closed SSE stream).
+pub fn remove_sink(id: u64) {
+    sinks()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .retain(|(sink_id, _, _)| *sink_id != id);
+}
+
+/// Forgets a session's subscriptions and sinks (HTTP `DELETE`, or eviction).
+pub fn end_session(session: &str) {
+    subscriptions()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .remove(session);
+    sinks()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .retain(|(_, sink_session, _)| sink_session != session);
+}
+
+/// `resources/subscribe`. Only resources that change in place can be
+/// subscribed to; per-frame and per-id URIs never change.
+pub fn subscribe(session: &str, params: &Value) -> Result<Value, RpcError> {
+    let uri = subscription_uri(params)?;
+    let mut subscriptions = subscriptions().lock().unwrap_or_else(|e| e.into_inner());
+    // The watcher idles while nobody is subscribed; start from the current state
+    // so changes made in the meantime are not announced as new. Holding the lock
+    // keeps the watcher from polling before the baseline is in place.
+    if let (true, Some(watched)) = (subscriptions.is_empty(), WATCHED.get()) {
+        *seen().lock().unwrap_or_else(|e| e.into_inner()) =
+            (watched.latest_frame_id(), watched.newest_nowframe_id());
+    }
+    subscriptions
+        .entry(session.to_string())
+        .or_default()
+        .insert(uri);
+    Ok(json!({}))
+}
+
+/// `resources/unsubscribe`; unknown URIs are ignored.
+pub fn unsubscribe(session: &str, params: &Value) -> Result<Value, RpcError> {
+    let uri = subscription_uri(params)?;
+    let mut subscriptions = subscriptions().lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(uris) = subscriptions.get_mut(session) {
+        uris.remove(&uri);
+        if uris.is_empty() {
+            subscriptions.remove(session);
+        }
+    }
+    Ok(json!({}))
+}
+
+/// Called after `latest.json` was rewritten to point at `frame_id`.
+pub fn latest_bundle_changed(frame_id: &str) {
+    let changed = {
+        let mut seen = seen().lock().unwrap_or_else(|e| e.into_inner());
+        let changed = seen.0.as_deref() != Some(frame_id);
+        seen.0 = Some(frame_id.to_string());
+        changed
+    };
+    if changed {
+        for uri in LATEST_URIS {
+            resource_updated(uri);
+        }
+    }
+}
+
+/// Called when the newest stored NowFrame is `id`.
+pub fn nowframe_stored(id: &str) {
+    let changed = {
+        let mut seen = seen().lock().unwrap_or_else(|e| e.into_inner());
+        let changed = seen.1.as_deref() != Some(id);
+        seen.1 = Some(id.to_string());
+        changed
+    };
+    if changed {
+        resource_updated(NOWFRAME_LATEST);
+    }
+}
+
+/// Polls `latest.json` and `data_nowframes` in the background while at least
+/// one session is subscribed; the first subscription records the state the
+/// watcher compares against.
+pub fn spawn_watcher(latest_path: PathBuf, nowframes_dir: PathBuf) {
+    let watched = WATCHED.get_or_init(|| Watched {
+        latest_path,
+        store: NowFrameStore::new(nowframes_dir, RetentionPolicy::default()),
+    });
+    thread::spawn(move || loop {
+        thread::sleep(POLL_INTERVAL);
+        let idle = subscriptions().lock().map(|s| s.is_empty()).unwrap_or(true);
+        if idle {
+            continue;
+        }
+        if let Some(frame_id) = watched.latest_frame_id() {
+            latest_bundle_changed(&frame_id);
+        }
+        if let Some(id) = watched.newest_nowframe_id() {
+            nowframe_stored(&id);
+        }
+    });
+}
+
+/// Sends the update to the sinks of the sessions subscribed to `uri` only.
+fn resource_updated(uri: &str) {
+    let sessions: BTreeSet<String> = subscriptions()
+        .lock()
+        .map(|s| {
+            s.iter()
+                .filter(|(_, uris)| uris.contains(uri))
+                .map(|(session, _)| session.clone())
+                .collect()
+        })
+        .unwrap_or_default();
+    if sessions.is_empty() {
+        return;
+    }
+    log_line(&format!(
+        "notify resources/updated uri={} sessions={}",
+        uri,
+        sessions.len()
+    ));
+    let message = json!({
+        "jsonrpc": "2.0",
+        "method": "notifications/resources/updated",
+        "params": { "uri": uri }
+    });
+    let sinks: Vec<Sink> = sinks()
+        .lock()
+        .map(|s| {
+            s.iter()
+                .filter(|(_, session, _)| sessions.contains(session))
+                .map(|(_, _, sink)| sink.clone())
+                .collect()
+        })
+        .unwrap_or_default();
+    for sink in sinks {
+        sink(&message);
+    }
+}
+
+fn subscription_uri(params: &Value) -> Result<String, RpcError> {
+    let uri = params
+        .get("uri")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| RpcError::invalid_request("missing uri"))?;
+    if uri == NOWFRAME_LATEST || LATEST_URIS.contains(&uri) {
+        return Ok(uri.to_string());
+    }
+    Err(RpcError::invalid_request(format!(
+        "not a subscribable resource (only screen://latest/* and nowframe://latest change): {}",
+        uri
+    )))
+}
+
+fn subscriptions() -> &'static Mutex<BTreeMap<String, BTreeSet<String>>> {
+    SUBSCRIPTIONS.get_or_init(|| Mutex::new(BTreeMap::new()))
+}
+
+fn sinks() -> &'static Mutex<Vec<(u64, String, Sink)>> {
+    SINKS.get_or_init(|| Mutex::new(Vec::new()))
+}
+
+fn seen() -> &'static Mutex<(Option<String>, Option<String>)> {
+    SEEN.get_or_init(|| Mutex::new((None, None)))
+}
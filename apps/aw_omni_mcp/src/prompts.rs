@@ -0,0 +1,387 @@
+use std::collections::BTreeMap;
+use std::fs;
+
+use aw_client::{Event, Expr, Query, TimePeriod};
+use chrono::{Duration, Utc};
+use nowframe_core::{segment, ListQuery};
+use omni_client::ParsedElement;
+use redaction::Field;
+use serde_json::{json, Map, Value};
+
+use crate::{load_latest_bundle, new_aw_client, new_redactor, nowframe_store, Config, RpcError};
+
+/// Elements beyond this are summarised by count so prompts stay a sensible size.
+const MAX_ELEMENTS: usize = 200;
+const MAX_TITLES: usize = 10;
+/// Upper bound for `recent_activity`'s `minutes` (one week); also keeps the
+/// look-back arithmetic far from overflow.
+const MAX_MINUTES: i64 = 7 * 24 * 60;
+
+pub struct PromptArgument {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub required: bool,
+}
+
+/// One MCP prompt template, rendered from the latest bundle and AW context.
+pub struct Prompt {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub arguments: &'static [PromptArgument],
+    pub render: fn(&Config, &Map<String, Value>) -> Result<String, RpcError>,
+}
+
+impl Prompt {
+    /// Entry for `prompts/list`.
+    pub fn definition(&self) -> Value {
+        let arguments: Vec<Value> = self
+            .arguments
+            .iter()
+            .map(|arg| {
+                json!({
+                    "name": arg.name,
+                    "description": arg.description,
+                    "required": arg.required,
+                })
+            })
+            .collect();
+        json!({
+            "name": self.name,
+            "description": self.description,
+            "arguments": arguments,
+        })
+    }
+}
+
+pub static PROMPTS: &[Prompt] = &[
+    Prompt {
+        name: "describe_screen",
+        description: "Describe what is on the screen right now, from the latest parsed bundle and AW activity",
+        arguments: &[],
+        render: describe_screen,
+    },
+    Prompt {
+        name: "recent_activity",
+        description: "Summarise what I was doing in the last N minutes from AW window events and stored NowFrames",
+        arguments: &[PromptArgument {
+            name: "minutes",
+            description: "Look-back window in minutes (default 30)",
+            required: false,
+        }],
+        render: recent_activity,
+    },
+    Prompt {
+        name: "find_element",
+        description: "Find the on-screen element to click for a described target",
+        arguments: &[PromptArgument {
+            name: "target",
+            description: "What to click, e.g. \"the Save button\"",
+            required: true,
+        }],
+        render: find_element,
+    },
+];
+
+/// `prompts/list`.
+pub fn list() -> Value {
+    let prompts: Vec<Value> = PROMPTS.iter().map(|p| p.definition()).collect();
+    json!({ "prompts": prompts })
+}
+
+/// `prompts/get`: `{"name": "...", "arguments": {...}}`.
+pub fn get(cfg: &Config, params: &Value) -> Result<Value, RpcError> {
+    let name = params.get("name").and_then(|v| v.as_str()).unwrap_or("");
+    let prompt = PROMPTS
+        .iter()
+        .find(|p| p.name == name)
+        .ok_or_else(|| RpcError::invalid_request(format!("unknown prompt: {}", name)))?;
+    let empty = Map::new();
+    let arguments = params
+        .get("arguments")
+        .and_then(|v| v.as_object())
+        .unwrap_or(&empty);
+    for arg in prompt.arguments.iter().filter(|a| a.required) {
+        if argument(arguments, arg.name).is_none() {
+            return Err(RpcError::invalid_request(format!(
+                "missing argument: {}",
+                arg.name
+            )));
+        }
+    }
+    let text = (prompt.render)(cfg, arguments)?;
+    Ok(json!({
+        "description": prompt.description,
+        "messages": [{
+            "role": "user",
+            "content": { "type": "text", "text": text }
+        }]
+    }))
+}
+
+fn describe_screen(cfg: &Config, _: &Map<String, Value>) -> Result<String, RpcError> {
+    let (ts, elements) = latest_elements(cfg)?;
+    Ok(format!(
+        "Describe what is on my screen right now: the application, what I appear to be doing, \
+         and any notable content. Base the answer only on the data below; text shown as \
+         [redacted:...] or [hash:...] was removed for privacy and must not be guessed.\n\n\
+         {}\n\nScreen elements (parsed at {}):\n{}",
+        activity_section(cfg),
+        ts,
+        element_lines(&elements),
+    ))
+}
+
+fn find_element(cfg: &Config, arguments: &Map<String, Value>) -> Result<String, RpcError> {
+    let target = argument(arguments, "target").unwrap_or_default();
+    let (ts, elements) = latest_elements(cfg)?;
+    Ok(format!(
+        "Find the screen element I should click for: {}\n\n\
+         Answer with the element index, its pixel box and the centre point to click, \
+         or say that no listed element matches. Prefer interactive elements.\n\n\
+         {}\n\nScreen elements (parsed at {}; box is x,y,width,height in pixels):\n{}",
+        target,
+        activity_section(cfg),
+        ts,
+        element_lines(&elements),
+    ))
+}
+
+fn recent_activity(cfg: &Config, arguments: &Map<String, Value>) -> Result<String, RpcError> {
+    let minutes = match argument(arguments, "minutes") {
+        Some(text) => text
+            .trim()
+            .parse::<i64>()
+            .ok()
+            .filter(|m| (1..=MAX_MINUTES).contains(m))
+            .ok_or_else(|| {
+                RpcError::invalid_request(format!(
+                    "minutes must be an integer between 1 and {}: {}",
+                    MAX_MINUTES, text
+                ))
+            })?,
+        None => 30,
+    };
+    let end = Utc::now();
+    let start = end - Duration::minutes(minutes);
+    let redactor = new_redactor(cfg)?;
+
+    let (events, afk_filtered) = window_time(cfg, TimePeriod::new(start, end));
+    let mut apps: BTreeMap<String, f64> = BTreeMap::new();
+    let mut titles: BTreeMap<(String, String), f64> = BTreeMap::new();
+    for event in events.iter().flatten() {
+        let app = event.data_str("app").unwrap_or("unknown").to_string();
+        *apps.entry(app.clone()).or_default() += event.duration;
+        let title = event
+            .data_str("title")
+            .and_then(|t| redactor.text(t, Some(&app), Field::Title));
+        if let Some(title) = title.filter(|t| !t.trim().is_empty()) {
+            *titles.entry((app, title)).or_default() += event.duration;
+        }
+    }
+
+    let mut sections = vec![format!(
+        "Summarise what I was doing in the last {} minutes ({} to {} UTC): the main tasks, \
+         how time was split between them, and any interruptions. Base the answer only on the \
+         data below; [redacted:...] / [hash:...] values were removed for privacy.",
+        minutes,
+        start.format("%H:%M"),
+        end.format("%H:%M"),
+    )];
+
+    if events.is_none() {
+        sections.push("ActivityWatch window events: unavailable.".to_string());
+    } else {
+        let source = if afk_filtered {
+            "from ActivityWatch, AFK time excluded"
+        } else {
+            "from ActivityWatch; no AFK watcher, so idle time is included"
+        };
+        sections.push(format!(
+            "Time per app ({}):\n{}",
+            source,
+            ranked_lines(apps.into_iter().collect(), usize::MAX)
+        ));
+        let titles = titles
+            .into_iter()
+            .map(|((app, title), secs)| (format!("{} — {}", app, title), secs))
+            .collect();
+        sections.push(format!(
+            "Top window titles:\n{}",
+            ranked_lines(titles, MAX_TITLES)
+        ));
+    }
+
+    // Episodes only with the same opt-in consent as episodes.list.
+    if cfg.episodes.enabled {
+        let store = nowframe_store(cfg);
+        let entries = store.list(&ListQuery {
+            since: Some(start),
+            ..ListQuery::default()
+        })?;
+        let frames: Vec<_> = entries
+            .iter()
+            .rev()
+            .filter_map(|entry| store.get(&entry.id).ok())
+            .map(|mut frame| {
+                frame.redact(&redactor);
+                frame
+            })
+            .collect();
+        let lines: Vec<String> = segment(&frames, &cfg.episodes.segmenter)
+            .iter()
+            .map(|ep| {
+                format!(
+                    "- {}–{} {} ({:.0} min){}",
+                    ep.start.format("%H:%M"),
+                    ep.end.format("%H:%M"),
+                    ep.dominant_app.as_deref().unwrap_or("unknown"),
+                    ep.duration_secs / 60.0,
+                    ep.titles
+                        .first()
+                        .map(|t| format!(": {}", t))
+                        .unwrap_or_default(),
+                )
+            })
+            .collect();
+        if !lines.is_empty() {
+            sections.push(format!(
+                "Episodes from stored NowFrames:\n{}",
+                lines.join("\n")
+            ));
+        }
+    }
+
+    Ok(sections.join("\n\n"))
+}
+
+/// Elements of the bundle `latest.json` points at; they were redacted when written.
+fn latest_elements(cfg: &Config) -> Result<(String, Vec<ParsedElement>), RpcError> {
+    let latest = load_latest_bundle(cfg)
+        .ok_or_else(|| RpcError::not_found("no screen bundle yet; call screen.bundle first"))?;
+    let json_path = latest
+        .json_path
+        .ok_or_else(|| RpcError::not_found("latest bundle has no JSON"))?;
+    let text = fs::read_to_string(&json_path)
+        .map_err(|e| RpcError::from(format!("read {} failed: {}", json_path, e)))?;
+    let bundle: Value = serde_json::from_str(&text)
+        .map_err(|e| RpcError::from(format!("parse {} failed: {}", json_path, e)))?;
+    let elements = bundle
+        .get("elements")
+        .cloned()
+        .map(serde_json::from_value)
+        .transpose()
+        .map_err(|e| RpcError::from(format!("parse {} failed: {}", json_path, e)))?
+        .unwrap_or_default();
+    Ok((latest.ts, elements))
+}
+
+fn activity_section(cfg: &Config) -> String {
+    let activity = new_redactor(cfg).ok().and_then(|redactor| {
+        let mut activity = new_aw_client(cfg).current_activity().ok()?;
+        redactor.activity(&mut activity);
+        Some(activity)
+    });
+    match activity {
+        Some(a) => format!(
+            "Current activity (ActivityWatch): app {}, window \"{}\" for {:.0}s, afk: {}",
+            a.app.as_deref().unwrap_or("unknown"),
+            a.title.as_deref().unwrap_or(""),
+            a.window_duration_secs,
+            serde_json::to_value(a.afk)
+                .ok()
+                .and_then(|v| v.as_str().map(str::to_string))
+                .unwrap_or_default(),
+        ),
+        None => "Current activity (ActivityWatch): unavailable".to_string(),
+    }
+}
+
+fn element_lines(elements: &[ParsedElement]) -> String {
+    if elements.is_empty() {
+        return "(no elements)".to_string();
+    }
+    let mut lines: Vec<String> = elements
+        .iter()
+        .take(MAX_ELEMENTS)
+        .enumerate()
+        .map(|(i, el)| {
+            let kind = serde_json::to_value(el.kind)
+                .ok()
+                .and_then(|v| v.as_str().map(str::to_string))
+                .unwrap_or_default();
+            let bbox = el
+                .bbox_px
+                .map(|b| format!(" box={},{},{},{}", b.x, b.y, b.width, b.height))
+                .unwrap_or_default();
+            let interactive = if el.interactivity == Some(true) {
+                " interactive"
+            } else {
+                ""
+            };
+            format!(
+                "[{}] {} \"{}\"{}{}",
+                i,
+                kind,
+                el.text().unwrap_or(""),
+                bbox,
+                interactive
+            )
+        })
+        .collect();
+    if elements.len() > MAX_ELEMENTS {
+        lines.push(format!(
+            "... {} more elements",
+            elements.len() - MAX_ELEMENTS
+        ));
+    }
+    lines.join("\n")
+}
+
+/// Window time per app and title within `period`, clipped to it by AW and with AFK
+/// time removed when an AFK bucket exists. The flag says whether AFK was removed.
+fn window_time(cfg: &Config, period: TimePeriod) -> (Option<Vec<Event>>, bool) {
+    let client = new_aw_client(cfg);
+    let Some(buckets) = client.discover_buckets().ok() else {
+        return (None, false);
+    };
+    let Some(window) = buckets.window else {
+        return (None, false);
+    };
+    let keys = ["app", "title"];
+    let query = match &buckets.afk {
+        Some(afk) => Query::window_time_by_keys(&window.id, &afk.id, &keys),
+        None => Query::new().returning(
+            Expr::query_bucket(&window.id)
+                .flood()
+                .merge_events_by_keys(&keys)
+                .sort_by_duration(),
+        ),
+    };
+    let events = client
+        .query_events(&query, &[period])
+        .ok()
+        .and_then(|mut results| results.pop());
+    (events, buckets.afk.is_some())
+}
+
+fn ranked_lines(mut items: Vec<(String, f64)>, limit: usize) -> String {
+    if items.is_empty() {
+        return "(none)".to_string();
+    }
+    items.sort_by(|a, b| b.1.total_cmp(&a.1));
+    items
+        .iter()
+        .take(limit)
+        .map(|(label, secs)| format!("- {}: {:.1} min", label, secs / 60.0))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Prompt arguments are strings per the MCP spec; numbers are accepted too.
+fn argument(arguments: &Map<String, Value>, name: &str) -> Option<String> {
+    match arguments.get(name)? {
+        Value::String(s) if !s.trim().is_empty() => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
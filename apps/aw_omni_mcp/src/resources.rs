@@ -0,0 +1,279 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use aw_client::EventQuery;
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+
+use crate::{
+    check_frame_id, encode_base64_with_limit, load_latest_bundle, load_nowframe, new_aw_client,
+    new_redactor, nowframe_store, Config, RpcError,
+};
+
+const PNG: &str = "image/png";
+const JSON: &str = "application/json";
+/// Variants stored per frame in `cache_screens` as `<frame_id>_<variant>.png`.
+const IMAGE_VARIANTS: &[&str] = &["raw", "annotated", "mask", "som"];
+const DEFAULT_EVENT_LIMIT: u64 = 100;
+
+/// `resources/list`: what can be read right now. AW buckets are listed
+/// best-effort and left out while AW is unreachable.
+pub fn list(cfg: &Config) -> Result<Value, RpcError> {
+    let mut resources = Vec::new();
+
+    if let Some(latest) = load_latest_bundle(cfg) {
+        let available = [
+            ("raw", true),
+            ("annotated", latest.annotated_path.is_some()),
+            ("mask", latest.mask_path.is_some()),
+            ("som", latest.som_path.is_some()),
+            ("json", latest.json_path.is_some()),
+        ];
+        for (variant, present) in available {
+            if present {
+                resources.push(json!({
+                    "uri": format!("screen://latest/{}", variant),
+                    "name": format!("Latest screen ({})", variant),
+                    "description": format!("Frame {}", latest.frame_id),
+                    "mimeType": variant_mime(variant),
+                }));
+            }
+        }
+    }
+
+    if let Ok(Some((entry, _))) = nowframe_store(cfg).latest() {
+        resources.push(json!({
+            "uri": "nowframe://latest",
+            "name": "Latest NowFrame",
+            "description": format!("Stored NowFrame {}", entry.id),
+            "mimeType": JSON,
+        }));
+    }
+
+    if let Ok(buckets) = new_aw_client(cfg).get_buckets() {
+        for bucket in buckets {
+            resources.push(json!({
+                "uri": format!("aw://bucket/{}/events", bucket.id),
+                "name": format!("{} events", bucket.id),
+                "description": format!("Latest {} events of this ActivityWatch bucket", DEFAULT_EVENT_LIMIT),
+                "mimeType": JSON,
+            }));
+        }
+    }
+
+    Ok(json!({ "resources": resources }))
+}
+
+/// `resources/templates/list`.
+pub fn templates() -> Value {
+    json!({
+        "resourceTemplates": [
+            {
+                "uriTemplate": "screen://latest/{variant}",
+                "name": "Latest screen",
+                "description": "variant: raw (redacted unless `unredacted: true`), annotated, mask, som (PNG) or json (bundle JSON)",
+            },
+            {
+                "uriTemplate": "screen://frame/{frame_id}/{variant}",
+                "name": "Cached screen frame",
+                "description": "A frame still in cache_screens; same variants as screen://latest",
+            },
+            {
+                "uriTemplate": "nowframe://{id}",
+                "name": "Stored NowFrame",
+                "description": "Id from nowframe.list, or `latest`",
+                "mimeType": JSON,
+            },
+            {
+                "uriTemplate": "aw://bucket/{bucket_id}/events{?limit,start,end}",
+                "name": "ActivityWatch bucket events",
+                "description": "Newest first, redacted; limit defaults to 100, start/end are RFC 3339",
+                "mimeType": JSON,
+            },
+        ]
+    })
+}
+
+/// `resources/read` (and legacy `resource.read`).
+pub fn read(cfg: &Config, params: Value) -> Result<Value, RpcError> {
+    let uri = params
+        .get("uri")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| RpcError::invalid_request("missing uri"))?;
+    let unredacted = params
+        .get("unredacted")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    if unredacted && !cfg.redaction.screenshots.allow_unredacted {
+        return Err(RpcError::consent_required(
+            "unredacted screenshots are disabled; set [redaction.screenshots] \
+             allow_unredacted = true to opt in",
+        ));
+    }
+
+    if let Some(variant) = uri.strip_prefix("screen://latest/") {
+        let path = latest_path(cfg, variant, unredacted)?;
+        return file_contents(uri, variant, &path);
+    }
+    if let Some(rest) = uri.strip_prefix("screen://frame/") {
+        let (frame_id, variant) = rest.split_once('/').ok_or_else(|| {
+            RpcError::invalid_request(format!(
+                "expected screen://frame/{{frame_id}}/{{variant}}: {}",
+                uri
+            ))
+        })?;
+        let path = frame_path(cfg, frame_id, variant, unredacted)?;
+        return file_contents(uri, variant, &path);
+    }
+    if let Some(id) = uri.strip_prefix("nowframe://") {
+        return nowframe_contents(cfg, uri, id);
+    }
+    if let Some(rest) = uri.strip_prefix("aw://bucket/") {
+        return aw_events_contents(cfg, uri, rest);
+    }
+    Err(RpcError::invalid_request(format!(
+        "unknown resource uri: {}",
+        uri
+    )))
+}
+
+fn latest_path(cfg: &Config, variant: &str, unredacted: bool) -> Result<PathBuf, RpcError> {
+    let latest =
+        load_latest_bundle(cfg).ok_or_else(|| RpcError::not_found("latest bundle not found"))?;
+    let path = match variant {
+        // The redacted variant is served unless the original is asked for explicitly;
+        // in strict mode the two are the same file.
+        "raw" => match latest.redacted_path {
+            Some(redacted) if !unredacted => Some(redacted),
+            _ => Some(latest.raw_path),
+        },
+        "annotated" => latest.annotated_path,
+        "mask" => latest.mask_path,
+        "som" => latest.som_path,
+        "json" => latest.json_path,
+        _ => {
+            return Err(RpcError::invalid_request(format!(
+                "unknown screen variant: {}",
+                variant
+            )))
+        }
+    };
+    path.map(PathBuf::from).ok_or_else(|| {
+        RpcError::not_found(format!("{} not available for the latest frame", variant))
+    })
+}
+
+fn frame_path(
+    cfg: &Config,
+    frame_id: &str,
+    variant: &str,
+    unredacted: bool,
+) -> Result<PathBuf, RpcError> {
+    check_frame_id(frame_id)?;
+    let dir = Path::new(&cfg.paths.cache_screens);
+    let file = |suffix: &str| dir.join(format!("{}_{}", frame_id, suffix));
+    let candidates = match variant {
+        "raw" if unredacted => vec![file("raw.png")],
+        "raw" => vec![file("redacted.png"), file("raw.png")],
+        "json" => vec![file("bundle.json"), file("parse.json")],
+        v if IMAGE_VARIANTS.contains(&v) => vec![file(&format!("{}.png", v))],
+        _ => {
+            return Err(RpcError::invalid_request(format!(
+                "unknown screen variant: {}",
+                variant
+            )))
+        }
+    };
+    candidates
+        .into_iter()
+        .find(|path| path.is_file())
+        .ok_or_else(|| {
+            RpcError::not_found(format!("{} not available for frame {}", variant, frame_id))
+        })
+}
+
+fn file_contents(uri: &str, variant: &str, path: &Path) -> Result<Value, RpcError> {
+    if variant == "json" {
+        let text = fs::read_to_string(path)
+            .map_err(|e| RpcError::from(format!("read {} failed: {}", path.display(), e)))?;
+        return Ok(text_contents(uri, text));
+    }
+    let blob = encode_base64_with_limit(path)?;
+    Ok(json!({
+        "contents": [{
+            "uri": uri,
+            "mimeType": PNG,
+            "blob": blob
+        }]
+    }))
+}
+
+fn nowframe_contents(cfg: &Config, uri: &str, id: &str) -> Result<Value, RpcError> {
+    let (_, frame) = load_nowframe(cfg, id)?;
+    let text = serde_json::to_string_pretty(&frame).map_err(|e| RpcError::from(e.to_string()))?;
+    Ok(text_contents(uri, text))
+}
+
+fn aw_events_contents(cfg: &Config, uri: &str, rest: &str) -> Result<Value, RpcError> {
+    let (path, query) = rest.split_once('?').unwrap_or((rest, ""));
+    let bucket_id = path
+        .strip_suffix("/events")
+        .filter(|id| !id.is_empty() && !id.contains('/'))
+        .ok_or_else(|| {
+            RpcError::invalid_request(format!(
+                "expected aw://bucket/{{bucket_id}}/events: {}",
+                uri
+            ))
+        })?;
+
+    let mut events_query = EventQuery::new().limit(DEFAULT_EVENT_LIMIT);
+    for pair in query.split('&').filter(|p| !p.is_empty()) {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        events_query = match key {
+            "limit" => events_query.limit(
+                value
+                    .parse()
+                    .map_err(|_| RpcError::invalid_request(format!("invalid limit: {}", value)))?,
+            ),
+            "start" => events_query.start(parse_time(key, value)?),
+            "end" => events_query.end(parse_time(key, value)?),
+            _ => {
+                return Err(RpcError::invalid_request(format!(
+                    "unknown query parameter: {}",
+                    key
+                )))
+            }
+        };
+    }
+
+    let mut events = new_aw_client(cfg).get_events(bucket_id, &events_query)?;
+    let redactor = new_redactor(cfg)?;
+    for event in events.iter_mut() {
+        redactor.event(event);
+    }
+    let text = serde_json::to_string_pretty(&events).map_err(|e| RpcError::from(e.to_string()))?;
+    Ok(text_contents(uri, text))
+}
+
+fn parse_time(key: &str, value: &str) -> Result<DateTime<Utc>, RpcError> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|t| t.with_timezone(&Utc))
+        .map_err(|e| RpcError::invalid_request(format!("{}: {}", key, e)))
+}
+
+fn text_contents(uri: &str, text: String) -> Value {
+    json!({
+        "contents": [{
+            "uri": uri,
+            "mimeType": JSON,
+            "text": text
+        }]
+    })
+}
+
+fn variant_mime(variant: &str) -> &'static str {
+    match variant {
+        "json" => JSON,
+        _ => PNG,
+    }
+}
@@ -0,0 +1,194 @@
+use nowframe_core::MAX_EPISODE_HOURS;
+use serde_json::{json, Value};
+
+use crate::{
+    aw_get_state, episodes_list, nowframe_build, nowframe_get, nowframe_list, screen_bundle,
+    screen_capture, screen_parse, system_health, Config, RpcError,
+};
+
+/// One MCP tool. The same table answers `tools/list`, `tools/call` and the
+/// legacy direct methods (`{"method":"screen.bundle",...}`).
+pub struct Tool {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub input_schema: fn() -> Value,
+    pub handler: fn(&Config, Value) -> Result<Value, RpcError>,
+}
+
+impl Tool {
+    /// Entry for `tools/list`.
+    pub fn definition(&self) -> Value {
+        json!({
+            "name": self.name,
+            "description": self.description,
+            "inputSchema": (self.input_schema)(),
+        })
+    }
+}
+
+pub static TOOLS: &[Tool] = &[
+    Tool {
+        name: "aw.get_state",
+        description:
+            "ActivityWatch server info, buckets and the current (redacted) window/AFK activity",
+        input_schema: no_arguments,
+        handler: |cfg, _| aw_get_state(cfg),
+    },
+    Tool {
+        name: "system.health",
+        description:
+            "Reachability of ActivityWatch and the OmniParser sidecar, plus preflight status",
+        input_schema: no_arguments,
+        handler: |cfg, _| system_health(cfg),
+    },
+    Tool {
+        name: "nowframe.build",
+        description:
+            "Build a NowFrame from the current activity and latest parsed screen without storing it",
+        input_schema: nowframe_build_schema,
+        handler: nowframe_build,
+    },
+    Tool {
+        name: "nowframe.list",
+        description: "List stored NowFrames, newest first",
+        input_schema: nowframe_list_schema,
+        handler: nowframe_list,
+    },
+    Tool {
+        name: "nowframe.get",
+        description: "Read one stored NowFrame by id, or the newest one",
+        input_schema: nowframe_get_schema,
+        handler: nowframe_get,
+    },
+    Tool {
+        name: "episodes.list",
+        description: "Segment recent NowFrames into episodes (requires [episodes] enabled = true)",
+        input_schema: episodes_list_schema,
+        handler: episodes_list,
+    },
+    Tool {
+        name: "screen.capture",
+        description: "Capture the screen to cache_screens without parsing it",
+        input_schema: screen_capture_schema,
+        handler: screen_capture,
+    },
+    Tool {
+        name: "screen.parse",
+        description: "Parse a captured frame (by frame_id or raw_path) into UI elements",
+        input_schema: screen_parse_schema,
+        handler: screen_parse,
+    },
+    Tool {
+        name: "screen.bundle",
+        description: "Capture, parse, and annotate the screen",
+        input_schema: screen_bundle_schema,
+        handler: screen_bundle,
+    },
+];
+
+pub fn find(name: &str) -> Option<&'static Tool> {
+    TOOLS.iter().find(|tool| tool.name == name)
+}
+
+fn object(properties: Value) -> Value {
+    json!({
+        "type": "object",
+        "properties": properties,
+        "required": []
+    })
+}
+
+fn no_arguments() -> Value {
+    object(json!({}))
+}
+
+fn capture_properties() -> Value {
+    json!({
+        "mode": { "type": "string", "enum": ["full", "active"], "default": "full" },
+        "format": { "type": "string", "enum": ["png"], "default": "png" },
+        "with_cursor": { "type": "boolean", "default": false }
+    })
+}
+
+fn parse_properties() -> Value {
+    json!({
+        "parse_options": {
+            "type": "object",
+            "description": "Passed through to the sidecar /parse request"
+        },
+        "wait_ready_ms": {
+            "type": "integer",
+            "minimum": 0,
+            "description": "Wait up to this long for the sidecar to become ready"
+        }
+    })
+}
+
+fn merge(mut base: Value, extra: Value) -> Value {
+    if let (Some(base), Value::Object(extra)) = (base.as_object_mut(), extra) {
+        base.extend(extra);
+    }
+    base
+}
+
+fn nowframe_build_schema() -> Value {
+    object(json!({
+        "reason": { "type": "string", "default": "mcp" },
+        "explain": {
+            "type": "boolean",
+            "default": false,
+            "description": "Attach the trigger rule evaluation for the current activity"
+        }
+    }))
+}
+
+fn nowframe_list_schema() -> Value {
+    object(json!({
+        "since": { "type": "string", "format": "date-time" },
+        "until": { "type": "string", "format": "date-time" },
+        "limit": { "type": "integer", "minimum": 0, "default": 50 }
+    }))
+}
+
+fn nowframe_get_schema() -> Value {
+    object(json!({
+        "id": {
+            "type": "string",
+            "default": "latest",
+            "description": "Id from nowframe.list, or \"latest\""
+        }
+    }))
+}
+
+fn episodes_list_schema() -> Value {
+    object(json!({
+        "hours": {
+            "type": "integer",
+            "minimum": 1,
+            "maximum": MAX_EPISODE_HOURS,
+            "default": 24
+        }
+    }))
+}
+
+fn screen_capture_schema() -> Value {
+    // parse_options / wait_ready_ms only matter in strict screenshot mode, where
+    // the capture is parsed before anything is written.
+    object(merge(capture_properties(), parse_properties()))
+}
+
+fn screen_parse_schema() -> Value {
+    object(merge(
+        json!({
+            "frame_id": { "type": "string" },
+            "raw_path": { "type": "string" }
+        }),
+        parse_properties(),
+    ))
+}
+
+fn screen_bundle_schema() -> Value {
+    let mut properties = merge(capture_properties(), parse_properties());
+    properties["include_b64"] = json!({ "type": "boolean", "default": false });
+    object(properties)
+}
@@ -0,0 +1,179 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{Event, HostBuckets};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AfkState {
+    Afk,
+    NotAfk,
+    Unknown,
+}
+
+impl AfkState {
+    fn from_event(event: Option<&Event>) -> Self {
+        match event.and_then(|e| e.data_str("status")) {
+            Some("afk") => AfkState::Afk,
+            Some("not-afk") => AfkState::NotAfk,
+            _ => AfkState::Unknown,
+        }
+    }
+}
+
+/// What the user is looking at right now, derived from the latest window and AFK events.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CurrentActivity {
+    pub hostname: String,
+    pub observed_at: DateTime<Utc>,
+    pub app: Option<String>,
+    pub title: Option<String>,
+    pub window_since: Option<DateTime<Utc>>,
+    pub window_duration_secs: f64,
+    pub afk: AfkState,
+    pub afk_since: Option<DateTime<Utc>>,
+    pub afk_duration_secs: f64,
+}
+
+impl CurrentActivity {
+    pub fn from_events(
+        buckets: &HostBuckets,
+        window_event: Option<&Event>,
+        afk_event: Option<&Event>,
+        observed_at: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            hostname: buckets.hostname.clone(),
+            observed_at,
+            app: window_event
+                .and_then(|e| e.data_str("app"))
+                .map(str::to_string),
+            title: window_event
+                .and_then(|e| e.data_str("title"))
+                .map(str::to_string),
+            window_since: window_event.map(|e| e.timestamp),
+            window_duration_secs: state_duration(window_event, observed_at),
+            afk: AfkState::from_event(afk_event),
+            afk_since: afk_event.map(|e| e.timestamp),
+            afk_duration_secs: state_duration(afk_event, observed_at),
+        }
+    }
+
+    pub fn is_afk(&self) -> bool {
+        self.afk == AfkState::Afk
+    }
+}
+
+// Heartbeats keep extending the latest event, so the state has lasted at least its
+// recorded duration and at most until now.
+fn state_duration(event: Option<&Event>, observed_at: DateTime<Utc>) -> f64 {
+    match event {
+        Some(event) => {
+            let elapsed = (observed_at - event.timestamp).num_milliseconds() as f64 / 1000.0;
+            elapsed.max(event.duration).max(0.0)
+        }
+        None => 0.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone};
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    fn at(secs: i64) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 5, 2, 8, 0, 0).unwrap() + Duration::seconds(secs)
+    }
+
+    fn event(secs: i64, duration: f64, data: Value) -> Event {
+        let mut event = Event::new(at(secs), data.as_object().cloned().unwrap_or_default());
+        event.duration = duration;
+        event
+    }
+
+    fn host() -> HostBuckets {
+        HostBuckets {
+            hostname: "host".to_string(),
+            ..HostBuckets::default()
+        }
+    }
+
+    #[test]
+    fn builds_from_window_and_afk_events() {
+        let window = event(0, 30.0, json!({ "app": "Code", "title": "main.rs" }));
+        let afk = event(-600, 0.0, json!({ "status": "not-afk" }));
+        let activity = CurrentActivity::from_events(&host(), Some(&window), Some(&afk), at(90));
+        assert_eq!(activity.hostname, "host");
+        assert_eq!(activity.observed_at, at(90));
+        assert_eq!(activity.app.as_deref(), Some("Code"));
+        assert_eq!(activity.title.as_deref(), Some("main.rs"));
+        assert_eq!(activity.window_since, Some(at(0)));
+        assert_eq!(activity.window_duration_secs, 90.0);
+        assert_eq!(activity.afk, AfkState::NotAfk);
+        assert_eq!(activity.afk_since, Some(at(-600)));
+        assert_eq!(activity.afk_duration_secs, 690.0);
+        assert!(!activity.is_afk());
+    }
+
+    #[test]
+    fn no_events_means_unknown_and_zero() {
+        let activity = CurrentActivity::from_events(&host(), None, None, at(0));
+        assert_eq!(activity.app, None);
+        assert_eq!(activity.title, None);
+        assert_eq!(activity.window_since, None);
+        assert_eq!(activity.window_duration_secs, 0.0);
+        assert_eq!(activity.afk, AfkState::Unknown);
+        assert_eq!(activity.afk_since, None);
+        assert_eq!(activity.afk_duration_secs, 0.0);
+    }
+
+    #[test]
+    fn afk_status_values() {
+        let cases = [
+            (json!({ "status": "afk" }), AfkState::Afk),
+            (json!({ "status": "not-afk" }), AfkState::NotAfk),
+            (json!({ "status": "away" }), AfkState::Unknown),
+            (json!({ "status": 1 }), AfkState::Unknown),
+            (json!({}), AfkState::Unknown),
+        ];
+        for (data, expected) in cases {
+            let afk = event(0, 0.0, data.clone());
+            let activity = CurrentActivity::from_events(&host(), None, Some(&afk), at(0));
+            assert_eq!(activity.afk, expected, "{}", data);
+            assert_eq!(activity.is_afk(), expected == AfkState::Afk);
+        }
+    }
+
+    #[test]
+    fn window_event_without_strings_has_no_app_or_title() {
+        let window = event(0, 0.0, json!({ "app": 3, "title": null }));
+        let activity = CurrentActivity::from_events(&host(), Some(&window), None, at(5));
+        assert_eq!(activity.app, None);
+        assert_eq!(activity.title, None);
+        assert_eq!(activity.window_since, Some(at(0)));
+    }
+
+    #[test]
+    fn state_duration_is_bounded_by_recorded_duration_and_now() {
+        let cases = [
+            // Elapsed since the event started wins when it is longer.
+            (event(0, 10.0, json!({})), at(60), 60.0),
+            // The recorded duration wins when the clock lags behind it.
+            (event(0, 120.0, json!({})), at(60), 120.0),
+            (event(0, 0.0, json!({})), at(0), 0.0),
+            // An event in the future never yields a negative duration.
+            (event(60, 0.0, json!({})), at(0), 0.0),
+            (
+                event(0, 0.0, json!({})),
+                at(0) + Duration::milliseconds(1500),
+                1.5,
+            ),
+        ];
+        for (event, observed_at, expected) in cases {
+            assert_eq!(state_duration(Some(&event), observed_at), expected);
+        }
+        assert_eq!(state_duration(None, at(0)), 0.0);
+    }
+}
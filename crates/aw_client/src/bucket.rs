@@ -0,0 +1,301 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Deserializer, Serialize};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Bucket {
+    pub id: String,
+    #[serde(rename = "type", default)]
+    pub bucket_type: String,
+    #[serde(default)]
+    pub client: String,
+    #[serde(default)]
+    pub hostname: String,
+    #[serde(default, deserialize_with = "lenient_datetime")]
+    pub created: Option<DateTime<Utc>>,
+    #[serde(default, deserialize_with = "lenient_datetime")]
+    pub last_updated: Option<DateTime<Utc>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WatcherKind {
+    Window,
+    Afk,
+    WebBrowser,
+    Editor,
+}
+
+impl WatcherKind {
+    fn bucket_types(self) -> &'static [&'static str] {
+        match self {
+            WatcherKind::Window => &["currentwindow"],
+            WatcherKind::Afk => &["afkstatus"],
+            WatcherKind::WebBrowser => &["web.tab.current"],
+            WatcherKind::Editor => &["app.editor.activity"],
+        }
+    }
+
+    fn client_prefixes(self) -> &'static [&'static str] {
+        match self {
+            WatcherKind::Window => &["aw-watcher-window"],
+            WatcherKind::Afk => &["aw-watcher-afk"],
+            WatcherKind::WebBrowser => &["aw-client-web", "aw-watcher-web"],
+            WatcherKind::Editor => &[
+                "aw-watcher-vscode",
+                "aw-watcher-vim",
+                "aw-watcher-jetbrains",
+                "aw-watcher-sublime",
+                "aw-watcher-emacs",
+            ],
+        }
+    }
+}
+
+impl Bucket {
+    /// Matches on the bucket `type` first and falls back to the watcher `client` name.
+    pub fn is_kind(&self, kind: WatcherKind) -> bool {
+        if kind
+            .bucket_types()
+            .iter()
+            .any(|t| self.bucket_type.eq_ignore_ascii_case(t))
+        {
+            return true;
+        }
+        let client = self.client.to_ascii_lowercase();
+        kind.client_prefixes()
+            .iter()
+            .any(|prefix| client.starts_with(prefix))
+    }
+
+    pub fn is_on_host(&self, hostname: &str) -> bool {
+        self.hostname.eq_ignore_ascii_case(hostname)
+    }
+}
+
+/// Buckets belonging to one host, grouped by watcher kind.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct HostBuckets {
+    pub hostname: String,
+    pub window: Option<Bucket>,
+    pub afk: Option<Bucket>,
+    pub web_browsers: Vec<Bucket>,
+    pub editors: Vec<Bucket>,
+}
+
+impl HostBuckets {
+    pub fn from_buckets(hostname: &str, buckets: &[Bucket]) -> Self {
+        Self {
+            hostname: hostname.to_string(),
+            window: find_bucket(buckets, WatcherKind::Window, hostname).cloned(),
+            afk: find_bucket(buckets, WatcherKind::Afk, hostname).cloned(),
+            web_browsers: filter_buckets(buckets, WatcherKind::WebBrowser, hostname)
+                .cloned()
+                .collect(),
+            editors: filter_buckets(buckets, WatcherKind::Editor, hostname)
+                .cloned()
+                .collect(),
+        }
+    }
+}
+
+pub fn filter_buckets<'a>(
+    buckets: &'a [Bucket],
+    kind: WatcherKind,
+    hostname: &'a str,
+) -> impl Iterator<Item = &'a Bucket> + 'a {
+    buckets
+        .iter()
+        .filter(move |b| b.is_kind(kind) && b.is_on_host(hostname))
+}
+
+/// Picks the most recently updated bucket of `kind` on `hostname`.
+pub fn find_bucket<'a>(
+    buckets: &'a [Bucket],
+    kind: WatcherKind,
+    hostname: &str,
+) -> Option<&'a Bucket> {
+    buckets
+        .iter()
+        .filter(|b| b.is_kind(kind) && b.is_on_host(hostname))
+        .max_by_key(|b| b.last_updated.or(b.created))
+}
+
+fn lenient_datetime<'de, D>(deserializer: D) -> Result<Option<DateTime<Utc>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let raw: Option<String> = Option::deserialize(deserializer)?;
+    Ok(raw.and_then(|s| {
+        DateTime::parse_from_rfc3339(&s)
+            .map(|dt| dt.with_timezone(&Utc))
+            .or_else(|_| {
+                // AW sometimes omits the offset; treat naive timestamps as UTC.
+                chrono::NaiveDateTime::parse_from_str(&s, "%Y-%m-%dT%H:%M:%S%.f")
+                    .map(|naive| naive.and_utc())
+            })
+            .ok()
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn bucket(id: &str, bucket_type: &str, client: &str, hostname: &str, updated: &str) -> Bucket {
+        serde_json::from_value(json!({
+            "id": id,
+            "type": bucket_type,
+            "client": client,
+            "hostname": hostname,
+            "last_updated": updated,
+        }))
+        .unwrap()
+    }
+
+    fn ids(buckets: &[Bucket]) -> Vec<&str> {
+        buckets.iter().map(|b| b.id.as_str()).collect()
+    }
+
+    fn fixture() -> Vec<Bucket> {
+        vec![
+            bucket(
+                "window_old",
+                "currentwindow",
+                "aw-watcher-window",
+                "host",
+                "2024-05-01T08:00:00Z",
+            ),
+            bucket(
+                "window_new",
+                "currentwindow",
+                "aw-watcher-window",
+                "host",
+                "2024-05-02T08:00:00Z",
+            ),
+            bucket(
+                "afk",
+                "afkstatus",
+                "aw-watcher-afk",
+                "HOST",
+                "2024-05-02T08:00:00Z",
+            ),
+            bucket(
+                "firefox",
+                "web.tab.current",
+                "aw-client-web",
+                "host",
+                "2024-05-02T08:00:00Z",
+            ),
+            bucket(
+                "chrome",
+                "web.tab.current",
+                "aw-client-web",
+                "host",
+                "2024-05-02T07:00:00Z",
+            ),
+            bucket(
+                "vscode",
+                "app.editor.activity",
+                "aw-watcher-vscode",
+                "host",
+                "2024-05-02T08:00:00Z",
+            ),
+            bucket(
+                "other_window",
+                "currentwindow",
+                "aw-watcher-window",
+                "other",
+                "2024-05-03T08:00:00Z",
+            ),
+            bucket(
+                "other_afk",
+                "afkstatus",
+                "aw-watcher-afk",
+                "other",
+                "2024-05-03T08:00:00Z",
+            ),
+        ]
+    }
+
+    #[test]
+    fn groups_one_hosts_buckets_by_kind() {
+        let host = HostBuckets::from_buckets("host", &fixture());
+        assert_eq!(host.hostname, "host");
+        assert_eq!(
+            host.window.as_ref().map(|b| b.id.as_str()),
+            Some("window_new")
+        );
+        // Hostnames compare case-insensitively.
+        assert_eq!(host.afk.as_ref().map(|b| b.id.as_str()), Some("afk"));
+        assert_eq!(ids(&host.web_browsers), ["firefox", "chrome"]);
+        assert_eq!(ids(&host.editors), ["vscode"]);
+    }
+
+    #[test]
+    fn other_hosts_are_kept_apart() {
+        let other = HostBuckets::from_buckets("other", &fixture());
+        assert_eq!(
+            other.window.as_ref().map(|b| b.id.as_str()),
+            Some("other_window")
+        );
+        assert_eq!(other.afk.as_ref().map(|b| b.id.as_str()), Some("other_afk"));
+        assert!(other.web_browsers.is_empty());
+        assert!(other.editors.is_empty());
+
+        let unknown = HostBuckets::from_buckets("nowhere", &fixture());
+        assert!(unknown.window.is_none() && unknown.afk.is_none());
+    }
+
+    #[test]
+    fn missing_afk_bucket_leaves_it_empty() {
+        let buckets: Vec<Bucket> = fixture().into_iter().filter(|b| b.id != "afk").collect();
+        let host = HostBuckets::from_buckets("host", &buckets);
+        assert!(host.afk.is_none());
+        assert_eq!(
+            host.window.as_ref().map(|b| b.id.as_str()),
+            Some("window_new")
+        );
+    }
+
+    #[test]
+    fn most_recent_falls_back_to_created() {
+        let buckets: Vec<Bucket> = serde_json::from_value(json!([
+            { "id": "a", "type": "currentwindow", "hostname": "h", "created": "2024-05-01T08:00:00Z" },
+            // Naive timestamps are read as UTC.
+            { "id": "b", "type": "currentwindow", "hostname": "h", "created": "2024-05-03T08:00:00.123" },
+            { "id": "c", "type": "currentwindow", "hostname": "h", "created": "2024-05-02T08:00:00Z" },
+        ]))
+        .unwrap();
+        let found = find_bucket(&buckets, WatcherKind::Window, "h").unwrap();
+        assert_eq!(found.id, "b");
+    }
+
+    #[test]
+    fn kind_matches_type_or_client_prefix() {
+        let cases = [
+            ("currentwindow", "", WatcherKind::Window, true),
+            ("CurrentWindow", "", WatcherKind::Window, true),
+            ("", "aw-watcher-window-wayland", WatcherKind::Window, true),
+            ("", "aw-watcher-afk", WatcherKind::Afk, true),
+            ("", "aw-watcher-web-chrome", WatcherKind::WebBrowser, true),
+            ("", "AW-Watcher-Vim", WatcherKind::Editor, true),
+            ("app.editor.activity", "", WatcherKind::Editor, true),
+            ("currentwindow", "", WatcherKind::Afk, false),
+            ("", "aw-watcher-input", WatcherKind::Window, false),
+            ("", "my-aw-watcher-window", WatcherKind::Window, false),
+        ];
+        for (bucket_type, client, kind, expected) in cases {
+            let b = bucket("b", bucket_type, client, "h", "2024-05-01T08:00:00Z");
+            assert_eq!(
+                b.is_kind(kind),
+                expected,
+                "{} {} {:?}",
+                bucket_type,
+                client,
+                kind
+            );
+        }
+    }
+}
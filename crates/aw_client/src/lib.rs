@@ -1,35 +1,358 @@
-use anyhow::{anyhow, Context, Result};
-use serde_json::Value;
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use http_transport::{Retry, Transport, TransportConfig};
+
+pub use http_transport::{ClientError, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+mod activity;
+mod bucket;
+mod query;
+
+pub use activity::{AfkState, CurrentActivity};
+pub use bucket::{filter_buckets, find_bucket, Bucket, HostBuckets, WatcherKind};
+pub use query::{Expr, Query, TimePeriod};
+
+pub const OMNI_WATCHER_CLIENT: &str = "aw-watcher-omni";
+pub const OMNI_BUCKET_TYPE: &str = "app.omni.frame";
 
 #[derive(Clone, Debug)]
 pub struct AwClient {
-    base_url: String,
+    transport: Transport,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Event {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<i64>,
+    pub timestamp: DateTime<Utc>,
+    #[serde(default)]
+    pub duration: f64,
+    #[serde(default)]
+    pub data: Map<String, Value>,
+}
+
+impl Event {
+    pub fn new(timestamp: DateTime<Utc>, data: Map<String, Value>) -> Self {
+        Self {
+            id: None,
+            timestamp,
+            duration: 0.0,
+            data,
+        }
+    }
+
+    pub fn end(&self) -> DateTime<Utc> {
+        self.timestamp + Duration::milliseconds((self.duration * 1000.0).round() as i64)
+    }
+
+    pub fn data_str(&self, key: &str) -> Option<&str> {
+        self.data.get(key).and_then(|v| v.as_str())
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct EventQuery {
+    pub start: Option<DateTime<Utc>>,
+    pub end: Option<DateTime<Utc>>,
+    pub limit: Option<u64>,
+}
+
+impl EventQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn start(mut self, start: DateTime<Utc>) -> Self {
+        self.start = Some(start);
+        self
+    }
+
+    pub fn end(mut self, end: DateTime<Utc>) -> Self {
+        self.end = Some(end);
+        self
+    }
+
+    pub fn limit(mut self, limit: u64) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    fn query_pairs(&self) -> Vec<(&'static str, String)> {
+        let mut pairs = Vec::new();
+        if let Some(start) = self.start {
+            pairs.push(("start", format_timestamp(start)));
+        }
+        if let Some(end) = self.end {
+            pairs.push(("end", format_timestamp(end)));
+        }
+        if let Some(limit) = self.limit {
+            pairs.push(("limit", limit.to_string()));
+        }
+        pairs
+    }
 }
 
 impl AwClient {
     pub fn new(base_url: impl Into<String>) -> Self {
+        Self::with_config(base_url, TransportConfig::default())
+    }
+
+    pub fn with_config(base_url: impl Into<String>, config: TransportConfig) -> Self {
         Self {
-            base_url: base_url.into(),
+            transport: Transport::new(base_url, config),
         }
     }
 
     pub fn get_info(&self) -> Result<Value> {
-        self.get_json("/api/0/info")
+        self.get_json("/api/0/info", &[])
+    }
+
+    pub fn get_hostname(&self) -> Result<String> {
+        let info = self.get_info()?;
+        info.get("hostname")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string())
+            .ok_or_else(|| ClientError::invalid_json("server info has no hostname"))
+    }
+
+    pub fn get_buckets(&self) -> Result<Vec<Bucket>> {
+        let value = self.get_json("/api/0/buckets", &[])?;
+        let map: Map<String, Value> =
+            serde_json::from_value(value).map_err(ClientError::invalid_json)?;
+        let mut buckets = Vec::with_capacity(map.len());
+        for (id, mut entry) in map {
+            if let Value::Object(fields) = &mut entry {
+                fields
+                    .entry("id")
+                    .or_insert_with(|| Value::String(id.clone()));
+            }
+            let bucket: Bucket = serde_json::from_value(entry)
+                .map_err(|e| ClientError::invalid_json(format!("bucket {}: {}", id, e)))?;
+            buckets.push(bucket);
+        }
+        Ok(buckets)
+    }
+
+    /// Locates the window/afk/browser/editor buckets of the AW server's own host.
+    pub fn discover_buckets(&self) -> Result<HostBuckets> {
+        let hostname = self.get_hostname()?;
+        let buckets = self.get_buckets()?;
+        Ok(HostBuckets::from_buckets(&hostname, &buckets))
+    }
+
+    pub fn get_events(&self, bucket_id: &str, query: &EventQuery) -> Result<Vec<Event>> {
+        let path = format!("/api/0/buckets/{}/events", encode_path_segment(bucket_id));
+        let value = self.get_json(&path, &query.query_pairs())?;
+        serde_json::from_value(value)
+            .map_err(|e| ClientError::invalid_json(format!("events of {}: {}", bucket_id, e)))
+    }
+
+    pub fn get_events_between(
+        &self,
+        bucket_id: &str,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<Event>> {
+        self.get_events(bucket_id, &EventQuery::new().start(start).end(end))
+    }
+
+    pub fn get_latest_events(&self, bucket_id: &str, limit: u64) -> Result<Vec<Event>> {
+        self.get_events(bucket_id, &EventQuery::new().limit(limit))
+    }
+
+    pub fn current_activity(&self) -> Result<CurrentActivity> {
+        let buckets = self.discover_buckets()?;
+        self.current_activity_for(&buckets)
+    }
+
+    /// Reads the latest window and AFK events of already-discovered buckets.
+    pub fn current_activity_for(&self, buckets: &HostBuckets) -> Result<CurrentActivity> {
+        let window_event = match &buckets.window {
+            Some(bucket) => self.get_latest_events(&bucket.id, 1)?.into_iter().next(),
+            None => None,
+        };
+        let afk_event = match &buckets.afk {
+            Some(bucket) => self.get_latest_events(&bucket.id, 1)?.into_iter().next(),
+            None => None,
+        };
+        Ok(CurrentActivity::from_events(
+            buckets,
+            window_event.as_ref(),
+            afk_event.as_ref(),
+            Utc::now(),
+        ))
+    }
+
+    /// Creates a bucket; AW answers 304 when it already exists, which is treated as success.
+    pub fn create_bucket(
+        &self,
+        bucket_id: &str,
+        bucket_type: &str,
+        client: &str,
+        hostname: &str,
+    ) -> Result<()> {
+        let path = format!("/api/0/buckets/{}", encode_path_segment(bucket_id));
+        let payload = json!({
+            "client": client,
+            "type": bucket_type,
+            "hostname": hostname,
+        });
+        self.post_no_content(&path, &[], &payload, Retry::Idempotent)
+    }
+
+    /// Sends a heartbeat; AW merges it into the previous event when the data is equal and
+    /// the gap is within `pulsetime_secs`. Only re-sent when the connection failed; a
+    /// heartbeat lost to a timeout is superseded by the next one.
+    pub fn heartbeat(&self, bucket_id: &str, event: &Event, pulsetime_secs: f64) -> Result<()> {
+        let path = format!(
+            "/api/0/buckets/{}/heartbeat",
+            encode_path_segment(bucket_id)
+        );
+        let payload = serde_json::to_value(event).map_err(ClientError::invalid_request)?;
+        self.post_no_content(
+            &path,
+            &[("pulsetime", pulsetime_secs.to_string())],
+            &payload,
+            Retry::ConnectOnly,
+        )
+    }
+
+    /// Ensures the `aw-watcher-omni_<host>` bucket exists and returns its id.
+    pub fn ensure_omni_bucket(&self) -> Result<String> {
+        let hostname = self.get_hostname()?;
+        let bucket_id = omni_bucket_id(&hostname);
+        self.create_bucket(&bucket_id, OMNI_BUCKET_TYPE, OMNI_WATCHER_CLIENT, &hostname)?;
+        Ok(bucket_id)
+    }
+
+    /// Runs a query2 program once per period; results are returned in period order.
+    pub fn query(&self, query: &Query, periods: &[TimePeriod]) -> Result<Vec<Value>> {
+        if periods.is_empty() {
+            return Err(ClientError::invalid_request(
+                "query requires at least one timeperiod",
+            ));
+        }
+        let payload = json!({
+            "query": query.statements(),
+            "timeperiods": periods.iter().map(|p| p.to_query_string()).collect::<Vec<_>>(),
+        });
+        // Queries are read-only, so repeating one is harmless.
+        let value = self.post_json("/api/0/query/", &payload, Retry::Idempotent)?;
+        match value {
+            Value::Array(items) => Ok(items),
+            other => Err(ClientError::invalid_json(format!(
+                "unexpected query response: {}",
+                other
+            ))),
+        }
+    }
+
+    pub fn query_events(&self, query: &Query, periods: &[TimePeriod]) -> Result<Vec<Vec<Event>>> {
+        self.query(query, periods)?
+            .into_iter()
+            .map(|value| serde_json::from_value(value).map_err(ClientError::invalid_json))
+            .collect()
     }
 
-    pub fn get_buckets(&self) -> Result<Value> {
-        self.get_json("/api/0/buckets")
+    fn get_json(&self, path: &str, query: &[(&str, String)]) -> Result<Value> {
+        self.transport.get_json(path, query)
+    }
+
+    fn post_no_content(
+        &self,
+        path: &str,
+        query: &[(&str, String)],
+        payload: &Value,
+        retry: Retry,
+    ) -> Result<()> {
+        self.transport.post_no_content(path, query, payload, retry)
+    }
+
+    fn post_json(&self, path: &str, payload: &Value, retry: Retry) -> Result<Value> {
+        self.transport.post_json(path, &[], payload, retry)
+    }
+}
+
+pub fn omni_bucket_id(hostname: &str) -> String {
+    format!("{}_{}", OMNI_WATCHER_CLIENT, hostname)
+}
+
+fn format_timestamp(ts: DateTime<Utc>) -> String {
+    ts.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+fn encode_path_segment(segment: &str) -> String {
+    let mut out = String::with_capacity(segment.len());
+    for byte in segment.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                out.push(byte as char)
+            }
+            _ => out.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn event_query_pairs_are_utc_millis() {
+        let start = Utc.with_ymd_and_hms(2024, 5, 2, 8, 0, 0).unwrap();
+        let query = EventQuery::new()
+            .start(start)
+            .end(start + Duration::milliseconds(1500))
+            .limit(10);
+        assert_eq!(
+            query.query_pairs(),
+            [
+                ("start", "2024-05-02T08:00:00.000Z".to_string()),
+                ("end", "2024-05-02T08:00:01.500Z".to_string()),
+                ("limit", "10".to_string()),
+            ]
+        );
+        assert!(EventQuery::new().query_pairs().is_empty());
+    }
+
+    #[test]
+    fn event_from_aw_json() {
+        let event: Event = serde_json::from_value(json!({
+            "id": 7,
+            "timestamp": "2024-05-02T10:00:00+02:00",
+            "duration": 2.25,
+            "data": { "app": "Code", "title": "main.rs" }
+        }))
+        .unwrap();
+        assert_eq!(event.id, Some(7));
+        assert_eq!(
+            event.timestamp,
+            Utc.with_ymd_and_hms(2024, 5, 2, 8, 0, 0).unwrap()
+        );
+        assert_eq!(event.end(), event.timestamp + Duration::milliseconds(2250));
+        assert_eq!(event.data_str("app"), Some("Code"));
+        assert_eq!(event.data_str("missing"), None);
+
+        // duration and data default when AW leaves them out.
+        let bare: Event =
+            serde_json::from_value(json!({ "timestamp": "2024-05-02T08:00:00Z" })).unwrap();
+        assert_eq!(bare.duration, 0.0);
+        assert!(bare.data.is_empty());
+        assert!(serde_json::from_value::<Event>(json!({ "duration": 1.0 })).is_err());
     }
 
-    fn get_json(&self, path: &str) -> Result<Value> {
-        let url = format!("{}{}", self.base_url.trim_end_matches('/'), path);
-        let response = ureq::get(&url)
-            .call()
-            .with_context(|| format!("GET {} failed", url))?;
-        let text = response
-            .into_string()
-            .map_err(|e| anyhow!("read response body failed: {}", e))?;
-        let value = serde_json::from_str(&text).map_err(|e| anyhow!("parse json failed: {}", e))?;
-        Ok(value)
+    #[test]
+    fn bucket_ids_are_percent_encoded() {
+        assert_eq!(
+            encode_path_segment("aw-watcher-window_host.local"),
+            "aw-watcher-window_host.local"
+        );
+        assert_eq!(encode_path_segment("a b/c?d"), "a%20b%2Fc%3Fd");
+        assert_eq!(encode_path_segment("hôte"), "h%C3%B4te");
     }
 }
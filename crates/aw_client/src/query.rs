@@ -0,0 +1,247 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde_json::Value;
+
+/// A single query2 expression, kept as source text.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Expr(String);
+
+impl Expr {
+    pub fn var(name: &str) -> Self {
+        Self(name.to_string())
+    }
+
+    pub fn query_bucket(bucket_id: &str) -> Self {
+        Self(format!("query_bucket({})", quote(bucket_id)))
+    }
+
+    pub fn find_bucket(filter: &str) -> Self {
+        Self(format!("find_bucket({})", quote(filter)))
+    }
+
+    pub fn filter_keyvals(self, key: &str, values: &[&str]) -> Self {
+        Self(format!(
+            "filter_keyvals({}, {}, {})",
+            self.0,
+            quote(key),
+            quote_list(values)
+        ))
+    }
+
+    pub fn exclude_keyvals(self, key: &str, values: &[&str]) -> Self {
+        Self(format!(
+            "exclude_keyvals({}, {}, {})",
+            self.0,
+            quote(key),
+            quote_list(values)
+        ))
+    }
+
+    pub fn filter_keyvals_regex(self, key: &str, regex: &str) -> Self {
+        Self(format!(
+            "filter_keyvals_regex({}, {}, {})",
+            self.0,
+            quote(key),
+            quote(regex)
+        ))
+    }
+
+    pub fn merge_events_by_keys(self, keys: &[&str]) -> Self {
+        Self(format!(
+            "merge_events_by_keys({}, {})",
+            self.0,
+            quote_list(keys)
+        ))
+    }
+
+    pub fn flood(self) -> Self {
+        Self(format!("flood({})", self.0))
+    }
+
+    pub fn filter_period_intersect(self, filter: Expr) -> Self {
+        Self(format!("filter_period_intersect({}, {})", self.0, filter.0))
+    }
+
+    pub fn period_union(self, other: Expr) -> Self {
+        Self(format!("period_union({}, {})", self.0, other.0))
+    }
+
+    pub fn union_no_overlap(self, other: Expr) -> Self {
+        Self(format!("union_no_overlap({}, {})", self.0, other.0))
+    }
+
+    pub fn sort_by_duration(self) -> Self {
+        Self(format!("sort_by_duration({})", self.0))
+    }
+
+    pub fn sort_by_timestamp(self) -> Self {
+        Self(format!("sort_by_timestamp({})", self.0))
+    }
+
+    pub fn limit_events(self, count: u64) -> Self {
+        Self(format!("limit_events({}, {})", self.0, count))
+    }
+
+    pub fn sum_durations(self) -> Self {
+        Self(format!("sum_durations({})", self.0))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// A query2 program: a list of assignments ending with `RETURN = ...;`.
+#[derive(Clone, Debug, Default)]
+pub struct Query {
+    statements: Vec<String>,
+}
+
+impl Query {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn assign(mut self, name: &str, expr: Expr) -> Self {
+        self.statements.push(format!("{} = {};", name, expr.0));
+        self
+    }
+
+    pub fn returning(mut self, expr: Expr) -> Self {
+        self.statements.push(format!("RETURN = {};", expr.0));
+        self
+    }
+
+    pub fn statements(&self) -> &[String] {
+        &self.statements
+    }
+
+    /// Non-AFK window time merged and summed per application.
+    pub fn window_time_by_app(window_bucket: &str, afk_bucket: &str) -> Self {
+        Self::window_time_by_keys(window_bucket, afk_bucket, &["app"])
+    }
+
+    /// Non-AFK window time merged on `keys` (e.g. `["app", "title"]`).
+    pub fn window_time_by_keys(window_bucket: &str, afk_bucket: &str, keys: &[&str]) -> Self {
+        Self::new()
+            .assign("afk_events", Expr::query_bucket(afk_bucket).flood())
+            .assign("window_events", Expr::query_bucket(window_bucket).flood())
+            .assign(
+                "not_afk",
+                Expr::var("afk_events").filter_keyvals("status", &["not-afk"]),
+            )
+            .assign(
+                "window_events",
+                Expr::var("window_events").filter_period_intersect(Expr::var("not_afk")),
+            )
+            .assign(
+                "merged",
+                Expr::var("window_events").merge_events_by_keys(keys),
+            )
+            .returning(Expr::var("merged").sort_by_duration())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimePeriod {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+impl TimePeriod {
+    pub fn new(start: DateTime<Utc>, end: DateTime<Utc>) -> Self {
+        Self { start, end }
+    }
+
+    pub fn to_query_string(&self) -> String {
+        format!(
+            "{}/{}",
+            self.start.to_rfc3339_opts(SecondsFormat::Millis, true),
+            self.end.to_rfc3339_opts(SecondsFormat::Millis, true)
+        )
+    }
+}
+
+fn quote(value: &str) -> String {
+    Value::String(value.to_string()).to_string()
+}
+
+fn quote_list(values: &[&str]) -> String {
+    let items: Vec<String> = values.iter().map(|v| quote(v)).collect();
+    format!("[{}]", items.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn window_time_by_app_text() {
+        let query = Query::window_time_by_app("aw-watcher-window_HOST", "aw-watcher-afk_HOST");
+        assert_eq!(
+            query.statements(),
+            [
+                r#"afk_events = flood(query_bucket("aw-watcher-afk_HOST"));"#,
+                r#"window_events = flood(query_bucket("aw-watcher-window_HOST"));"#,
+                r#"not_afk = filter_keyvals(afk_events, "status", ["not-afk"]);"#,
+                "window_events = filter_period_intersect(window_events, not_afk);",
+                r#"merged = merge_events_by_keys(window_events, ["app"]);"#,
+                "RETURN = sort_by_duration(merged);",
+            ]
+        );
+    }
+
+    #[test]
+    fn window_time_by_keys_lists_every_key() {
+        let query = Query::window_time_by_keys("w", "a", &["app", "title"]);
+        assert_eq!(
+            query.statements()[4],
+            r#"merged = merge_events_by_keys(window_events, ["app", "title"]);"#
+        );
+    }
+
+    #[test]
+    fn strings_are_escaped() {
+        let expr = Expr::query_bucket(r#"aw-watcher-window_my "host"\pc"#)
+            .filter_keyvals("title", &["a \"quoted\" title", "tab\there", "new\nline"])
+            .filter_keyvals_regex("app", r"^Code\.exe$");
+        assert_eq!(
+            expr.as_str(),
+            r#"filter_keyvals_regex(filter_keyvals(query_bucket("aw-watcher-window_my \"host\"\\pc"), "title", ["a \"quoted\" title", "tab\there", "new\nline"]), "app", "^Code\\.exe$")"#
+        );
+    }
+
+    #[test]
+    fn builder_composes_in_order() {
+        let query = Query::new()
+            .assign("events", Expr::find_bucket("aw-watcher-web").flood())
+            .returning(
+                Expr::var("events")
+                    .exclude_keyvals("url", &[])
+                    .sort_by_timestamp()
+                    .limit_events(5),
+            );
+        assert_eq!(
+            query.statements(),
+            [
+                r#"events = flood(find_bucket("aw-watcher-web"));"#,
+                r#"RETURN = limit_events(sort_by_timestamp(exclude_keyvals(events, "url", [])), 5);"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn time_period_is_utc_millis() {
+        let start = Utc.with_ymd_and_hms(2026, 10, 17, 4, 0, 0).unwrap();
+        let end = chrono::FixedOffset::east_opt(2 * 3600)
+            .unwrap()
+            .with_ymd_and_hms(2026, 10, 17, 6, 30, 15)
+            .unwrap()
+            .with_timezone(&Utc);
+        assert_eq!(
+            TimePeriod::new(start, end).to_query_string(),
+            "2026-10-17T04:00:00.000Z/2026-10-17T04:30:15.000Z"
+        );
+    }
+}
@@ -0,0 +1,221 @@
+use std::io;
+
+use thiserror::Error;
+
+pub type Result<T> = std::result::Result<T, ClientError>;
+
+#[derive(Debug, Error)]
+pub enum ClientError {
+    #[error("{url} unreachable: {message}")]
+    Unreachable { url: String, message: String },
+    #[error("{url} timed out")]
+    Timeout { url: String },
+    #[error("HTTP {code}: {body}")]
+    HttpStatus { code: u16, body: String },
+    #[error("invalid json: {message}")]
+    InvalidJson { message: String },
+    #[error("service not ready: {reason}")]
+    NotReady { reason: String },
+    #[error("payload too large (limit {limit} bytes)")]
+    PayloadTooLarge { limit: u64 },
+    #[error("invalid request: {message}")]
+    InvalidRequest { message: String },
+}
+
+impl ClientError {
+    pub fn invalid_json(message: impl ToString) -> Self {
+        ClientError::InvalidJson {
+            message: message.to_string(),
+        }
+    }
+
+    pub fn invalid_request(message: impl ToString) -> Self {
+        ClientError::InvalidRequest {
+            message: message.to_string(),
+        }
+    }
+
+    /// Stable snake_case name, used as the `kind` in JSON-RPC error data.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ClientError::Unreachable { .. } => "unreachable",
+            ClientError::Timeout { .. } => "timeout",
+            ClientError::HttpStatus { .. } => "http_status",
+            ClientError::InvalidJson { .. } => "invalid_json",
+            ClientError::NotReady { .. } => "not_ready",
+            ClientError::PayloadTooLarge { .. } => "payload_too_large",
+            ClientError::InvalidRequest { .. } => "invalid_request",
+        }
+    }
+
+    /// Whether the failure is transient. The transport only acts on this for
+    /// `Retry::Idempotent` requests.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            ClientError::Unreachable { .. }
+            | ClientError::Timeout { .. }
+            | ClientError::NotReady { .. } => true,
+            ClientError::HttpStatus { code, .. } => {
+                crate::classify_status(*code) == crate::RetryClass::Retry
+            }
+            ClientError::InvalidJson { .. }
+            | ClientError::PayloadTooLarge { .. }
+            | ClientError::InvalidRequest { .. } => false,
+        }
+    }
+
+    pub(crate) fn from_ureq(url: &str, err: ureq::Error, max_body_bytes: u64) -> Self {
+        match err {
+            ureq::Error::Status(413, _) => ClientError::PayloadTooLarge {
+                limit: max_body_bytes,
+            },
+            ureq::Error::Status(code, response) => {
+                let body = crate::read_body(response, ERROR_BODY_LIMIT).unwrap_or_default();
+                not_ready_from_status(code, &body).unwrap_or(ClientError::HttpStatus { code, body })
+            }
+            ureq::Error::Transport(transport) => {
+                let timed_out = std::error::Error::source(&transport)
+                    .and_then(|source| source.downcast_ref::<io::Error>())
+                    .map(|io_err| {
+                        matches!(
+                            io_err.kind(),
+                            io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock
+                        )
+                    })
+                    .unwrap_or(false);
+                if timed_out {
+                    ClientError::Timeout {
+                        url: url.to_string(),
+                    }
+                } else {
+                    ClientError::Unreachable {
+                        url: url.to_string(),
+                        message: transport.to_string(),
+                    }
+                }
+            }
+        }
+    }
+}
+
+const ERROR_BODY_LIMIT: u64 = 4 * 1024;
+
+// The sidecar answers 503 `{ "error": "preflight_only", "reason": ... }` while imports or
+// weights are still missing; surface that as NotReady instead of a bare status.
+fn not_ready_from_status(code: u16, body: &str) -> Option<ClientError> {
+    if code != 503 {
+        return None;
+    }
+    let value: serde_json::Value = serde_json::from_str(body).ok()?;
+    if value.get("error").and_then(|v| v.as_str()) != Some("preflight_only") {
+        return None;
+    }
+    let reason = value
+        .get("reason")
+        .and_then(|v| v.as_str())
+        .unwrap_or("preflight_only")
+        .to_string();
+    Some(ClientError::NotReady { reason })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{classify_status, RetryClass};
+
+    fn status(code: u16, body: &str) -> ClientError {
+        let response = ureq::Response::new(code, "status", body).unwrap();
+        ClientError::from_ureq(
+            "http://sidecar/parse",
+            ureq::Error::Status(code, response),
+            1024,
+        )
+    }
+
+    fn agent(read_timeout_ms: u64) -> ureq::Agent {
+        ureq::AgentBuilder::new()
+            .timeout_connect(Duration::from_millis(500))
+            .timeout_read(Duration::from_millis(read_timeout_ms))
+            .build()
+    }
+
+    #[test]
+    fn status_classes() {
+        for code in [408, 425, 429, 500, 502, 503, 504, 599] {
+            assert_eq!(classify_status(code), RetryClass::Retry, "{code}");
+        }
+        for code in [200, 304, 400, 401, 403, 404, 413, 422, 501, 505] {
+            assert_eq!(classify_status(code), RetryClass::Fail, "{code}");
+        }
+    }
+
+    #[test]
+    fn statuses_map_to_variants() {
+        let err = status(404, "no such bucket");
+        assert!(
+            matches!(&err, ClientError::HttpStatus { code: 404, body } if body == "no such bucket")
+        );
+        assert!(!err.is_retryable());
+
+        let err = status(500, "");
+        assert!(matches!(err, ClientError::HttpStatus { code: 500, .. }));
+        assert!(err.is_retryable());
+        assert_eq!(err.kind(), "http_status");
+
+        let err = status(413, "");
+        assert!(matches!(err, ClientError::PayloadTooLarge { limit: 1024 }));
+    }
+
+    #[test]
+    fn preflight_only_503_is_not_ready() {
+        let err = status(
+            503,
+            r#"{"ok": false, "error": "preflight_only", "reason": "weights_missing"}"#,
+        );
+        assert!(matches!(&err, ClientError::NotReady { reason } if reason == "weights_missing"));
+        assert_eq!(err.kind(), "not_ready");
+
+        let err = status(503, r#"{"ok": false, "error": "preflight_only"}"#);
+        assert!(matches!(&err, ClientError::NotReady { reason } if reason == "preflight_only"));
+
+        // Any other 503 stays a plain, retryable status.
+        for body in [
+            r#"{"ok": false, "error": "init_failed"}"#,
+            "Service Unavailable",
+        ] {
+            assert!(matches!(
+                status(503, body),
+                ClientError::HttpStatus { code: 503, .. }
+            ));
+        }
+        let err = status(500, r#"{"error": "preflight_only"}"#);
+        assert!(matches!(err, ClientError::HttpStatus { code: 500, .. }));
+    }
+
+    #[test]
+    fn timeout_and_refused_connections() {
+        // Accepts the connection but never answers.
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let err = agent(100).get(&url).call().unwrap_err();
+        let err = ClientError::from_ureq(&url, err, 1024);
+        assert!(
+            matches!(&err, ClientError::Timeout { url: u } if *u == url),
+            "{err}"
+        );
+        assert!(err.is_retryable());
+        drop(listener);
+
+        // Nothing listens on the port any more.
+        let err = agent(100).get(&url).call().unwrap_err();
+        let err = ClientError::from_ureq(&url, err, 1024);
+        assert!(
+            matches!(&err, ClientError::Unreachable { url: u, .. } if *u == url),
+            "{err}"
+        );
+        assert_eq!(err.kind(), "unreachable");
+    }
+}
@@ -0,0 +1,348 @@
+use std::io::Read;
+use std::thread;
+use std::time::Duration;
+
+use serde::Deserialize;
+use serde_json::Value;
+
+mod error;
+
+pub use error::{ClientError, Result};
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct TransportConfig {
+    pub connect_timeout_ms: u64,
+    pub read_timeout_ms: u64,
+    pub max_retries: u32,
+    pub initial_backoff_ms: u64,
+    pub max_backoff_ms: u64,
+    pub max_body_bytes: u64,
+}
+
+impl Default for TransportConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout_ms: 2_000,
+            read_timeout_ms: 30_000,
+            max_retries: 2,
+            initial_backoff_ms: 200,
+            max_backoff_ms: 5_000,
+            max_body_bytes: 64 * 1024 * 1024,
+        }
+    }
+}
+
+impl TransportConfig {
+    /// Delay before retry number `attempt` (0-based): doubles each time, capped at max.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 1u64.checked_shl(attempt.min(16)).unwrap_or(u64::MAX);
+        let ms = self
+            .initial_backoff_ms
+            .saturating_mul(factor)
+            .min(self.max_backoff_ms);
+        Duration::from_millis(ms)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RetryClass {
+    Retry,
+    Fail,
+}
+
+/// 408/425/429 and 5xx except 501/505 are transient; everything else is final.
+pub fn classify_status(code: u16) -> RetryClass {
+    match code {
+        408 | 425 | 429 => RetryClass::Retry,
+        501 | 505 => RetryClass::Fail,
+        500..=599 => RetryClass::Retry,
+        _ => RetryClass::Fail,
+    }
+}
+
+/// Which failures a request may be re-sent after; chosen by the caller per endpoint.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Retry {
+    /// Safe to repeat: timeouts, unreachable and transient statuses are retried.
+    Idempotent,
+    /// Only retried when the request never reached the server (DNS / connect failure).
+    /// Used for POSTs whose effect or cost must not be repeated, e.g. `/parse`.
+    ConnectOnly,
+}
+
+impl Retry {
+    /// Whether `err` may be followed by another attempt; `connect_failed` is set when
+    /// the request never left this machine.
+    fn allows(self, err: &ClientError, connect_failed: bool) -> bool {
+        match self {
+            // NotReady is never retried here: callers that want to wait for readiness
+            // (`OmniClient::parse_when_ready`) own that loop and its deadline.
+            Retry::Idempotent => err.is_retryable() && !matches!(err, ClientError::NotReady { .. }),
+            Retry::ConnectOnly => connect_failed,
+        }
+    }
+}
+
+/// A keep-alive ureq agent bound to one base URL, with timeouts and bounded retries.
+#[derive(Clone, Debug)]
+pub struct Transport {
+    base_url: String,
+    agent: ureq::Agent,
+    config: TransportConfig,
+}
+
+impl Transport {
+    pub fn new(base_url: impl Into<String>, config: TransportConfig) -> Self {
+        let agent = ureq::AgentBuilder::new()
+            .timeout_connect(Duration::from_millis(config.connect_timeout_ms))
+            .timeout_read(Duration::from_millis(config.read_timeout_ms))
+            .timeout_write(Duration::from_millis(config.read_timeout_ms))
+            .build();
+        Self {
+            base_url: base_url.into(),
+            agent,
+            config,
+        }
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    pub fn config(&self) -> &TransportConfig {
+        &self.config
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url.trim_end_matches('/'), path)
+    }
+
+    pub fn get_json(&self, path: &str, query: &[(&str, String)]) -> Result<Value> {
+        let url = self.url(path);
+        let response = self.send_with_retry(&url, Retry::Idempotent, || {
+            let mut request = self.agent.get(&url);
+            for (key, value) in query {
+                request = request.query(key, value);
+            }
+            request.call().map_err(Box::new)
+        })?;
+        self.read_json(response)
+    }
+
+    pub fn post_json(
+        &self,
+        path: &str,
+        query: &[(&str, String)],
+        payload: &Value,
+        retry: Retry,
+    ) -> Result<Value> {
+        let url = self.url(path);
+        let response = self.send_with_retry(&url, retry, || {
+            self.post_request(&url, query)
+                .send_json(payload.clone())
+                .map_err(Box::new)
+        })?;
+        self.read_json(response)
+    }
+
+    pub fn post_no_content(
+        &self,
+        path: &str,
+        query: &[(&str, String)],
+        payload: &Value,
+        retry: Retry,
+    ) -> Result<()> {
+        let url = self.url(path);
+        self.send_with_retry(&url, retry, || {
+            self.post_request(&url, query)
+                .send_json(payload.clone())
+                .map_err(Box::new)
+        })?;
+        Ok(())
+    }
+
+    fn post_request(&self, url: &str, query: &[(&str, String)]) -> ureq::Request {
+        let mut request = self.agent.post(url);
+        for (key, value) in query {
+            request = request.query(key, value);
+        }
+        request
+    }
+
+    fn send_with_retry<F>(&self, url: &str, retry: Retry, mut send: F) -> Result<ureq::Response>
+    where
+        F: FnMut() -> std::result::Result<ureq::Response, Box<ureq::Error>>,
+    {
+        let mut attempt = 0;
+        loop {
+            let err = match send() {
+                Ok(response) => return Ok(response),
+                Err(err) => err,
+            };
+            let hint = retry_after(&err);
+            let connect_failed = matches!(
+                err.kind(),
+                ureq::ErrorKind::Dns | ureq::ErrorKind::ConnectionFailed
+            );
+            let err = ClientError::from_ureq(url, *err, self.config.max_body_bytes);
+            if attempt >= self.config.max_retries || !retry.allows(&err, connect_failed) {
+                return Err(err);
+            }
+            let mut delay = self.config.backoff(attempt);
+            if let Some(hint) = hint {
+                delay = hint.min(Duration::from_millis(self.config.max_backoff_ms));
+            }
+            thread::sleep(delay);
+            attempt += 1;
+        }
+    }
+
+    fn read_json(&self, response: ureq::Response) -> Result<Value> {
+        let text = read_body(response, self.config.max_body_bytes)?;
+        serde_json::from_str(&text).map_err(ClientError::invalid_json)
+    }
+}
+
+fn retry_after(err: &ureq::Error) -> Option<Duration> {
+    match err {
+        ureq::Error::Status(_, response) => response
+            .header("retry-after")
+            .and_then(|v| v.trim().parse::<u64>().ok())
+            .map(Duration::from_secs),
+        ureq::Error::Transport(_) => None,
+    }
+}
+
+pub(crate) fn read_body(response: ureq::Response, limit: u64) -> Result<String> {
+    let url = response.get_url().to_string();
+    let mut bytes = Vec::new();
+    response
+        .into_reader()
+        .take(limit.saturating_add(1))
+        .read_to_end(&mut bytes)
+        .map_err(|e| ClientError::Unreachable {
+            url,
+            message: format!("read response body failed: {}", e),
+        })?;
+    if bytes.len() as u64 > limit {
+        return Err(ClientError::PayloadTooLarge { limit });
+    }
+    String::from_utf8(bytes).map_err(ClientError::invalid_json)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::net::TcpListener;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use serde_json::json;
+
+    use super::*;
+
+    fn config(max_retries: u32) -> TransportConfig {
+        TransportConfig {
+            connect_timeout_ms: 500,
+            read_timeout_ms: 500,
+            max_retries,
+            initial_backoff_ms: 1,
+            max_backoff_ms: 2,
+            ..TransportConfig::default()
+        }
+    }
+
+    /// A local server that counts connections and answers each with `reply`
+    /// (or just hangs up when `reply` is empty).
+    fn server(reply: &'static str) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let hits = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&hits);
+        thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                counter.fetch_add(1, Ordering::SeqCst);
+                let mut buf = [0u8; 4096];
+                let _ = std::io::Read::read(&mut stream, &mut buf);
+                let _ = stream.write_all(reply.as_bytes());
+            }
+        });
+        (url, hits)
+    }
+
+    const UNAVAILABLE: &str =
+        "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 4\r\nConnection: close\r\n\r\nbusy";
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        let config = TransportConfig::default();
+        let delays: Vec<u64> = (0..7)
+            .map(|a| config.backoff(a).as_millis() as u64)
+            .collect();
+        assert_eq!(delays, [200, 400, 800, 1600, 3200, 5000, 5000]);
+        assert_eq!(config.backoff(64), Duration::from_millis(5000));
+        assert_eq!(config.backoff(u32::MAX), Duration::from_millis(5000));
+
+        let huge = TransportConfig {
+            initial_backoff_ms: u64::MAX / 2,
+            max_backoff_ms: u64::MAX,
+            ..TransportConfig::default()
+        };
+        assert_eq!(huge.backoff(40), Duration::from_millis(u64::MAX));
+    }
+
+    #[test]
+    fn retry_policies() {
+        let timeout = ClientError::Timeout {
+            url: "u".to_string(),
+        };
+        let unreachable = ClientError::Unreachable {
+            url: "u".to_string(),
+            message: "refused".to_string(),
+        };
+        let status = |code| ClientError::HttpStatus {
+            code,
+            body: String::new(),
+        };
+        let not_ready = ClientError::NotReady {
+            reason: "weights_missing".to_string(),
+        };
+
+        assert!(Retry::Idempotent.allows(&timeout, false));
+        assert!(Retry::Idempotent.allows(&unreachable, true));
+        assert!(Retry::Idempotent.allows(&status(503), false));
+        assert!(Retry::Idempotent.allows(&status(429), false));
+        assert!(!Retry::Idempotent.allows(&status(404), false));
+        assert!(!Retry::Idempotent.allows(&status(501), false));
+        assert!(!Retry::Idempotent.allows(&not_ready, false));
+        assert!(!Retry::Idempotent.allows(&ClientError::invalid_json("x"), false));
+
+        // Only a failed connect proves the server never saw the request.
+        assert!(Retry::ConnectOnly.allows(&unreachable, true));
+        for err in [&timeout, &unreachable, &status(503), &status(429)] {
+            assert!(!Retry::ConnectOnly.allows(err, false), "{err}");
+        }
+    }
+
+    #[test]
+    fn connect_only_never_resends_a_request_that_arrived() {
+        for reply in [UNAVAILABLE, ""] {
+            let (url, hits) = server(reply);
+            let transport = Transport::new(url, config(3));
+            let result = transport.post_json("/parse", &[], &json!({}), Retry::ConnectOnly);
+            assert!(result.is_err());
+            assert_eq!(hits.load(Ordering::SeqCst), 1, "reply {reply:?}");
+        }
+    }
+
+    #[test]
+    fn idempotent_requests_are_retried_up_to_the_limit() {
+        let (url, hits) = server(UNAVAILABLE);
+        let err = Transport::new(url, config(2))
+            .get_json("/", &[])
+            .unwrap_err();
+        assert!(matches!(err, ClientError::HttpStatus { code: 503, .. }));
+        assert_eq!(hits.load(Ordering::SeqCst), 3);
+    }
+}
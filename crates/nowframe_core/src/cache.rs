@@ -0,0 +1,450 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+
+const LATEST_FILE: &str = "latest.json";
+const FRAME_PREFIX: &str = "frame_";
+const GIB: u64 = 1024 * 1024 * 1024;
+const MIB: u64 = 1024 * 1024;
+
+#[derive(Debug, Error)]
+pub enum CacheError {
+    #[error("cache io failed: {path}: {source}")]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+}
+
+impl CacheError {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            CacheError::Io { .. } => "io",
+        }
+    }
+
+    fn io(path: &Path, source: io::Error) -> Self {
+        CacheError::Io {
+            path: path.to_path_buf(),
+            source,
+        }
+    }
+}
+
+/// Limits for one cache directory; 0 disables a limit.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CacheLimits {
+    pub max_bytes: u64,
+    pub max_age_secs: u64,
+}
+
+impl CacheLimits {
+    fn new(max_bytes: u64, max_age_secs: u64) -> Self {
+        Self {
+            max_bytes,
+            max_age_secs,
+        }
+    }
+}
+
+impl Default for CacheLimits {
+    fn default() -> Self {
+        Self::new(0, 0)
+    }
+}
+
+/// `[cache]` budget; the defaults add up to the 5–8 GB soft cap in `DATA_GOVERNANCE.md`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CachePolicy {
+    pub screens: CacheLimits,
+    pub thumbs: CacheLimits,
+    pub tmp: CacheLimits,
+}
+
+impl Default for CachePolicy {
+    fn default() -> Self {
+        Self {
+            screens: CacheLimits::new(5 * GIB, 7 * 24 * 60 * 60),
+            thumbs: CacheLimits::new(512 * MIB, 7 * 24 * 60 * 60),
+            tmp: CacheLimits::new(512 * MIB, 24 * 60 * 60),
+        }
+    }
+}
+
+/// Current usage of one cache directory.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct CacheStats {
+    pub name: String,
+    pub path: String,
+    /// Frames (all artefacts sharing a frame id) plus loose files.
+    pub units: usize,
+    pub files: usize,
+    pub bytes: u64,
+    pub max_bytes: u64,
+    pub max_age_secs: u64,
+    pub oldest_used: Option<DateTime<Utc>>,
+    pub newest_used: Option<DateTime<Utc>>,
+    /// Frame kept regardless of limits because `latest.json` points at it.
+    pub protected: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct GcReport {
+    pub name: String,
+    pub dry_run: bool,
+    pub removed_units: usize,
+    pub removed_files: usize,
+    pub removed_bytes: u64,
+    pub expired_units: usize,
+    pub remaining_units: usize,
+    pub remaining_bytes: u64,
+    pub protected: Option<String>,
+}
+
+/// All files of one frame (`<frame_id>_raw.png`, `_annotated.png`, `_parse.json`, ...)
+/// or a single loose file, evicted together.
+struct Unit {
+    key: String,
+    files: Vec<PathBuf>,
+    bytes: u64,
+    last_used: SystemTime,
+}
+
+struct CacheDir {
+    name: &'static str,
+    path: PathBuf,
+    limits: CacheLimits,
+}
+
+/// Size and age budgets for `cache_screens`, `cache_thumbs` and `cache_tmp`.
+///
+/// Eviction is least-recently-used per frame: a frame's last use is the newest
+/// access (or, where the filesystem does not record access, modification)
+/// time among its files. The frame referenced by `cache_screens/latest.json`
+/// and `latest.json` itself are never deleted.
+pub struct CacheManager {
+    dirs: Vec<CacheDir>,
+    latest: PathBuf,
+}
+
+impl CacheManager {
+    pub fn new(
+        screens: impl Into<PathBuf>,
+        thumbs: impl Into<PathBuf>,
+        tmp: impl Into<PathBuf>,
+        policy: &CachePolicy,
+    ) -> Self {
+        let screens = screens.into();
+        Self {
+            latest: screens.join(LATEST_FILE),
+            dirs: vec![
+                CacheDir {
+                    name: "screens",
+                    path: screens,
+                    limits: policy.screens.clone(),
+                },
+                CacheDir {
+                    name: "thumbs",
+                    path: thumbs.into(),
+                    limits: policy.thumbs.clone(),
+                },
+                CacheDir {
+                    name: "tmp",
+                    path: tmp.into(),
+                    limits: policy.tmp.clone(),
+                },
+            ],
+        }
+    }
+
+    pub fn stats(&self) -> Result<Vec<CacheStats>, CacheError> {
+        let protected = self.protected_frame();
+        let mut all = Vec::new();
+        for dir in &self.dirs {
+            let units = scan(&dir.path)?;
+            all.push(CacheStats {
+                name: dir.name.to_string(),
+                path: dir.path.to_string_lossy().into_owned(),
+                units: units.len(),
+                files: units.iter().map(|u| u.files.len()).sum(),
+                bytes: units.iter().map(|u| u.bytes).sum(),
+                max_bytes: dir.limits.max_bytes,
+                max_age_secs: dir.limits.max_age_secs,
+                oldest_used: units.first().map(|u| DateTime::from(u.last_used)),
+                newest_used: units.last().map(|u| DateTime::from(u.last_used)),
+                protected: protected
+                    .clone()
+                    .filter(|id| units.iter().any(|u| &u.key == id)),
+            });
+        }
+        Ok(all)
+    }
+
+    /// Deletes units unused for longer than `max_age_secs`, then the least
+    /// recently used ones until each directory fits in `max_bytes`.
+    pub fn gc(&self, now: DateTime<Utc>, dry_run: bool) -> Result<Vec<GcReport>, CacheError> {
+        let protected = self.protected_frame();
+        let mut reports = Vec::new();
+        for dir in &self.dirs {
+            reports.push(gc_dir(dir, protected.as_deref(), now, dry_run)?);
+        }
+        Ok(reports)
+    }
+
+    /// Frame id named by `latest.json`, if it exists and is readable.
+    fn protected_frame(&self) -> Option<String> {
+        let text = fs::read_to_string(&self.latest).ok()?;
+        let latest: Value = serde_json::from_str(&text).ok()?;
+        latest
+            .get("frame_id")
+            .and_then(|v| v.as_str())
+            .map(str::to_string)
+    }
+}
+
+fn gc_dir(
+    dir: &CacheDir,
+    protected: Option<&str>,
+    now: DateTime<Utc>,
+    dry_run: bool,
+) -> Result<GcReport, CacheError> {
+    let units = scan(&dir.path)?;
+    // An age too large to subtract from `now` is no age limit at all.
+    let cutoff: Option<SystemTime> = i64::try_from(dir.limits.max_age_secs)
+        .ok()
+        .and_then(Duration::try_seconds)
+        .and_then(|age| now.checked_sub_signed(age))
+        .map(Into::into);
+    let mut total: u64 = units.iter().map(|u| u.bytes).sum();
+    let mut report = GcReport {
+        name: dir.name.to_string(),
+        dry_run,
+        protected: protected
+            .filter(|id| units.iter().any(|u| u.key == *id))
+            .map(str::to_string),
+        ..GcReport::default()
+    };
+
+    // Units are sorted least recently used first.
+    for unit in &units {
+        let keep = protected == Some(unit.key.as_str()) || unit.key == LATEST_FILE;
+        let expired =
+            dir.limits.max_age_secs > 0 && cutoff.is_some_and(|cutoff| unit.last_used < cutoff);
+        let over_budget = dir.limits.max_bytes > 0 && total > dir.limits.max_bytes;
+        if keep || !(expired || over_budget) {
+            report.remaining_units += 1;
+            continue;
+        }
+        if !dry_run {
+            for path in &unit.files {
+                match fs::remove_file(path) {
+                    Ok(()) => {}
+                    Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+                    Err(err) => return Err(CacheError::io(path, err)),
+                }
+            }
+        }
+        total -= unit.bytes;
+        report.removed_units += 1;
+        report.removed_files += unit.files.len();
+        report.removed_bytes += unit.bytes;
+        if expired {
+            report.expired_units += 1;
+        }
+    }
+
+    report.remaining_bytes = total;
+    Ok(report)
+}
+
+/// Top-level files of `dir` grouped into units, least recently used first.
+/// Subdirectories are left alone.
+fn scan(dir: &Path) -> Result<Vec<Unit>, CacheError> {
+    let read_dir = match fs::read_dir(dir) {
+        Ok(read_dir) => read_dir,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(CacheError::io(dir, err)),
+    };
+    let mut units: BTreeMap<String, Unit> = BTreeMap::new();
+    for item in read_dir {
+        let item = item.map_err(|e| CacheError::io(dir, e))?;
+        let Ok(metadata) = item.metadata() else {
+            continue;
+        };
+        if !metadata.is_file() {
+            continue;
+        }
+        let name = item.file_name().to_string_lossy().into_owned();
+        let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+        let used = metadata.accessed().map_or(modified, |a| a.max(modified));
+        let key = unit_key(&name);
+        let unit = units.entry(key.clone()).or_insert_with(|| Unit {
+            key,
+            files: Vec::new(),
+            bytes: 0,
+            last_used: SystemTime::UNIX_EPOCH,
+        });
+        unit.files.push(item.path());
+        unit.bytes += metadata.len();
+        unit.last_used = unit.last_used.max(used);
+    }
+    let mut units: Vec<Unit> = units.into_values().collect();
+    units.sort_by(|a, b| {
+        a.last_used
+            .cmp(&b.last_used)
+            .then_with(|| a.key.cmp(&b.key))
+    });
+    Ok(units)
+}
+
+/// `frame_20250101_120000_3_annotated.png` belongs to frame `frame_20250101_120000_3`;
+/// anything else is its own unit.
+fn unit_key(name: &str) -> String {
+    if name.starts_with(FRAME_PREFIX) {
+        if let Some((frame_id, _)) = name.rsplit_once('_') {
+            if frame_id.len() > FRAME_PREFIX.len() {
+                return frame_id.to_string();
+            }
+        }
+    }
+    name.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{File, FileTimes};
+
+    use tempfile::TempDir;
+
+    use super::*;
+
+    const FRAMES: [&str; 2] = ["frame_20261017_040000_0", "frame_20261017_040100_0"];
+    const ARTEFACTS: [&str; 3] = ["raw.png", "annotated.png", "parse.json"];
+
+    /// A screens dir with two frames of 300 bytes each, last used an hour and
+    /// a minute ago, and `latest.json` pointing at the older one.
+    fn screens() -> (TempDir, DateTime<Utc>) {
+        let dir = tempfile::tempdir().unwrap();
+        let now = Utc::now();
+        for (frame, age) in FRAMES.iter().zip([3600, 60]) {
+            for artefact in ARTEFACTS {
+                write(&dir, &format!("{}_{}", frame, artefact), 100, now, age);
+            }
+        }
+        let latest = format!(r#"{{"frame_id":"{}"}}"#, FRAMES[0]);
+        fs::write(dir.path().join(LATEST_FILE), latest).unwrap();
+        set_age(&dir.path().join(LATEST_FILE), now, 7200);
+        (dir, now)
+    }
+
+    fn write(dir: &TempDir, name: &str, bytes: usize, now: DateTime<Utc>, age_secs: i64) {
+        let path = dir.path().join(name);
+        fs::write(&path, vec![0u8; bytes]).unwrap();
+        set_age(&path, now, age_secs);
+    }
+
+    fn set_age(path: &Path, now: DateTime<Utc>, age_secs: i64) {
+        let at: SystemTime = (now - Duration::seconds(age_secs)).into();
+        File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_times(FileTimes::new().set_accessed(at).set_modified(at))
+            .unwrap();
+    }
+
+    fn manager(dir: &TempDir, limits: CacheLimits) -> CacheManager {
+        let policy = CachePolicy {
+            screens: limits,
+            thumbs: CacheLimits::default(),
+            tmp: CacheLimits::default(),
+        };
+        let missing = dir.path().join("missing");
+        CacheManager::new(dir.path(), &missing, &missing, &policy)
+    }
+
+    fn exists(dir: &TempDir, frame: &str) -> Vec<bool> {
+        ARTEFACTS
+            .iter()
+            .map(|a| dir.path().join(format!("{}_{}", frame, a)).exists())
+            .collect()
+    }
+
+    #[test]
+    fn latest_frame_and_latest_json_survive() {
+        let (dir, now) = screens();
+        let report = &manager(&dir, CacheLimits::new(1, 1))
+            .gc(now, false)
+            .unwrap()[0];
+
+        assert_eq!(report.protected.as_deref(), Some(FRAMES[0]));
+        assert_eq!(report.removed_units, 1);
+        assert_eq!(exists(&dir, FRAMES[0]), [true; 3]);
+        assert_eq!(exists(&dir, FRAMES[1]), [false; 3]);
+        assert!(dir.path().join(LATEST_FILE).exists());
+    }
+
+    #[test]
+    fn evicts_every_artefact_of_a_frame_together() {
+        let (dir, now) = screens();
+        fs::remove_file(dir.path().join(LATEST_FILE)).unwrap();
+        // Room for one frame: the least recently used one goes, all of it.
+        let report = &manager(&dir, CacheLimits::new(400, 0))
+            .gc(now, false)
+            .unwrap()[0];
+
+        assert_eq!(report.removed_units, 1);
+        assert_eq!(report.removed_files, 3);
+        assert_eq!(report.removed_bytes, 300);
+        assert_eq!(report.expired_units, 0);
+        assert_eq!(exists(&dir, FRAMES[0]), [false; 3]);
+        assert_eq!(exists(&dir, FRAMES[1]), [true; 3]);
+    }
+
+    #[test]
+    fn expires_by_last_use() {
+        let (dir, now) = screens();
+        fs::remove_file(dir.path().join(LATEST_FILE)).unwrap();
+        let report = &manager(&dir, CacheLimits::new(0, 600))
+            .gc(now, false)
+            .unwrap()[0];
+
+        assert_eq!(report.expired_units, 1);
+        assert_eq!(exists(&dir, FRAMES[0]), [false; 3]);
+        assert_eq!(exists(&dir, FRAMES[1]), [true; 3]);
+    }
+
+    #[test]
+    fn dry_run_deletes_nothing() {
+        let (dir, now) = screens();
+        fs::remove_file(dir.path().join(LATEST_FILE)).unwrap();
+        let report = &manager(&dir, CacheLimits::new(1, 1)).gc(now, true).unwrap()[0];
+
+        assert!(report.dry_run);
+        assert_eq!(report.removed_units, 2);
+        assert_eq!(report.removed_files, 6);
+        assert_eq!(exists(&dir, FRAMES[0]), [true; 3]);
+        assert_eq!(exists(&dir, FRAMES[1]), [true; 3]);
+    }
+
+    #[test]
+    fn huge_max_age_is_no_age_limit() {
+        let (dir, now) = screens();
+        let report = &manager(&dir, CacheLimits::new(0, u64::MAX))
+            .gc(now, false)
+            .unwrap()[0];
+
+        assert_eq!(report.removed_units, 0);
+        assert_eq!(report.remaining_units, 3);
+    }
+}
@@ -0,0 +1,445 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use aw_client::AfkState;
+
+use crate::NowFrame;
+
+/// Longest look-back accepted when segmenting on request (31 days). Callers
+/// validate against it so `now - hours` can never overflow.
+pub const MAX_EPISODE_HOURS: i64 = 31 * 24;
+const MAX_KEY_ELEMENTS: usize = 10;
+const MAX_TITLES: usize = 10;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SegmenterConfig {
+    /// Fallback for frames without AFK data: a gap between consecutive frames longer
+    /// than this closes the episode unless the later frame's window spans it.
+    pub idle_timeout_secs: u64,
+    /// Also split when the title changes within the same app.
+    pub split_on_title_change: bool,
+    /// A detour to another app shorter than this is folded into the surrounding
+    /// episode when it returns to the same app.
+    pub interruption_secs: u64,
+}
+
+impl Default for SegmenterConfig {
+    fn default() -> Self {
+        Self {
+            idle_timeout_secs: 300,
+            split_on_title_change: false,
+            interruption_secs: 60,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EpisodeBoundary {
+    AppChange,
+    TitleChange,
+    Afk,
+    IdleGap,
+    /// The frame sequence ended while the episode was still open.
+    Open,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AppTime {
+    pub app: String,
+    pub secs: f64,
+    pub frames: usize,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct KeyElement {
+    pub text: String,
+    pub frames: usize,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Episode {
+    pub id: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub duration_secs: f64,
+    pub dominant_app: Option<String>,
+    /// Time per app, largest first.
+    pub apps: Vec<AppTime>,
+    pub titles: Vec<String>,
+    /// Screen texts seen in the most frames.
+    pub key_elements: Vec<KeyElement>,
+    pub frame_count: usize,
+    pub ended_by: EpisodeBoundary,
+}
+
+struct Point<'a> {
+    at: DateTime<Utc>,
+    frame: &'a NowFrame,
+}
+
+impl Point<'_> {
+    fn app(&self) -> Option<&str> {
+        self.frame.activity.as_ref().and_then(|a| a.app.as_deref())
+    }
+
+    fn title(&self) -> Option<&str> {
+        self.frame
+            .activity
+            .as_ref()
+            .and_then(|a| a.title.as_deref())
+    }
+
+    fn is_afk(&self) -> bool {
+        self.frame.activity.as_ref().is_some_and(|a| a.is_afk())
+    }
+
+    /// Whether the user was away at some point since `last`. Frames are only stored
+    /// when a trigger fires, so their spacing says little; AW's AFK state does. A
+    /// not-afk period that began after `last` means an AFK period sat in between.
+    /// Without AFK data, a window open since before `last` shows continuous use,
+    /// and otherwise a gap longer than `idle` counts as idle.
+    fn idle_since(&self, last: &Point, idle: Duration) -> bool {
+        let Some(activity) = self.frame.activity.as_ref() else {
+            return self.at - last.at > idle;
+        };
+        if activity.afk == AfkState::NotAfk {
+            if let Some(active_since) = activity.afk_since {
+                return active_since > last.at;
+            }
+        }
+        let window_since = match activity.window_since {
+            Some(since) => Some(since),
+            None => Duration::try_milliseconds((activity.window_duration_secs * 1000.0) as i64)
+                .and_then(|open_for| self.at.checked_sub_signed(open_for)),
+        };
+        let spans_gap = window_since.is_none_or(|since| since <= last.at);
+        !spans_gap && self.at - last.at > idle
+    }
+}
+
+struct Segment<'a> {
+    points: Vec<Point<'a>>,
+    end: DateTime<Utc>,
+    ended_by: EpisodeBoundary,
+}
+
+impl Segment<'_> {
+    fn start(&self) -> DateTime<Utc> {
+        self.points[0].at
+    }
+
+    fn duration_secs(&self) -> f64 {
+        secs_between(self.start(), self.end)
+    }
+
+    fn only_app(&self) -> Option<&str> {
+        self.points[0].app()
+    }
+}
+
+/// Groups frames into episodes. AFK frames end the current episode and belong to none.
+pub fn segment(frames: &[NowFrame], config: &SegmenterConfig) -> Vec<Episode> {
+    let mut points: Vec<Point> = frames
+        .iter()
+        .map(|frame| Point {
+            at: frame.timestamp,
+            frame,
+        })
+        .collect();
+    points.sort_by_key(|p| p.at);
+
+    let idle = i64::try_from(config.idle_timeout_secs)
+        .ok()
+        .and_then(Duration::try_seconds)
+        .unwrap_or(Duration::MAX);
+    let mut segments: Vec<Segment> = Vec::new();
+    let mut current: Vec<Point> = Vec::new();
+
+    for point in points {
+        let boundary = match current.last() {
+            None => None,
+            Some(_) if point.is_afk() => Some(EpisodeBoundary::Afk),
+            Some(last) if point.idle_since(last, idle) => Some(EpisodeBoundary::IdleGap),
+            Some(last) if point.app() != last.app() => Some(EpisodeBoundary::AppChange),
+            Some(last) if config.split_on_title_change && point.title() != last.title() => {
+                Some(EpisodeBoundary::TitleChange)
+            }
+            Some(_) => None,
+        };
+        if let Some(ended_by) = boundary {
+            let last_at = current.last().map(|p| p.at).unwrap_or(point.at);
+            // A switch ends the episode at the moment it is observed; AFK and idle
+            // gaps end it at the last active frame.
+            let end = match ended_by {
+                EpisodeBoundary::AppChange | EpisodeBoundary::TitleChange => point.at,
+                _ => last_at,
+            };
+            segments.push(Segment {
+                points: std::mem::take(&mut current),
+                end,
+                ended_by,
+            });
+        }
+        if !point.is_afk() {
+            current.push(point);
+        }
+    }
+    if let Some(last) = current.last() {
+        let end = last.at;
+        segments.push(Segment {
+            points: current,
+            end,
+            ended_by: EpisodeBoundary::Open,
+        });
+    }
+
+    merge_interruptions(segments, config)
+        .iter()
+        .map(summarize)
+        .collect()
+}
+
+/// Folds A, B, A into one episode when B was a short app switch.
+fn merge_interruptions<'a>(
+    segments: Vec<Segment<'a>>,
+    config: &SegmenterConfig,
+) -> Vec<Segment<'a>> {
+    let mut out: Vec<Segment> = Vec::new();
+    let mut iter = segments.into_iter().peekable();
+    while let Some(segment) = iter.next() {
+        let foldable = out.last().is_some_and(|prev| {
+            prev.ended_by == EpisodeBoundary::AppChange
+                && segment.ended_by == EpisodeBoundary::AppChange
+                && segment.duration_secs() < config.interruption_secs as f64
+                && iter
+                    .peek()
+                    .is_some_and(|next| next.only_app() == prev.only_app())
+        });
+        if foldable {
+            let next = iter.next().expect("peeked");
+            let prev = out.last_mut().expect("checked");
+            prev.points.extend(segment.points);
+            prev.points.extend(next.points);
+            prev.end = next.end;
+            prev.ended_by = next.ended_by;
+        } else {
+            out.push(segment);
+        }
+    }
+    out
+}
+
+fn summarize(segment: &Segment) -> Episode {
+    let mut app_time: HashMap<String, AppTime> = HashMap::new();
+    let mut titles: Vec<String> = Vec::new();
+    let mut element_frames: HashMap<String, usize> = HashMap::new();
+
+    for (i, point) in segment.points.iter().enumerate() {
+        let until = segment
+            .points
+            .get(i + 1)
+            .map(|next| next.at)
+            .unwrap_or(segment.end);
+        // Idle time between frames already split the episode, so the gap up to the
+        // next frame is time spent in this frame's app.
+        let secs = secs_between(point.at, until);
+        let app = point.app().unwrap_or("unknown").to_string();
+        let entry = app_time.entry(app.clone()).or_insert(AppTime {
+            app,
+            secs: 0.0,
+            frames: 0,
+        });
+        entry.secs += secs;
+        entry.frames += 1;
+
+        if let Some(title) = point.title() {
+            if titles.len() < MAX_TITLES && !titles.iter().any(|t| t == title) {
+                titles.push(title.to_string());
+            }
+        }
+        if let Some(screen) = point.frame.screen.as_ref() {
+            for text in &screen.key_texts {
+                *element_frames.entry(text.clone()).or_insert(0) += 1;
+            }
+        }
+    }
+
+    let mut apps: Vec<AppTime> = app_time.into_values().collect();
+    apps.sort_by(|a, b| {
+        b.secs
+            .total_cmp(&a.secs)
+            .then(b.frames.cmp(&a.frames))
+            .then(a.app.cmp(&b.app))
+    });
+    let mut key_elements: Vec<KeyElement> = element_frames
+        .into_iter()
+        .map(|(text, frames)| KeyElement { text, frames })
+        .collect();
+    key_elements.sort_by(|a, b| b.frames.cmp(&a.frames).then(a.text.cmp(&b.text)));
+    key_elements.truncate(MAX_KEY_ELEMENTS);
+
+    let start = segment.start();
+    Episode {
+        id: format!("ep_{}", start.format("%Y%m%d_%H%M%S_%3f")),
+        start,
+        end: segment.end,
+        duration_secs: segment.duration_secs(),
+        dominant_app: apps.first().map(|a| a.app.clone()),
+        apps,
+        titles,
+        key_elements,
+        frame_count: segment.points.len(),
+        ended_by: segment.ended_by,
+    }
+}
+
+fn secs_between(from: DateTime<Utc>, to: DateTime<Utc>) -> f64 {
+    ((to - from).num_milliseconds() as f64 / 1000.0).max(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use aw_client::CurrentActivity;
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn at(secs: i64) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 3, 2, 9, 0, 0).unwrap() + Duration::seconds(secs)
+    }
+
+    /// A frame at `secs` whose window was opened at that moment and no AFK data.
+    fn frame(secs: i64, app: &str, title: &str) -> NowFrame {
+        let mut frame = NowFrame::new("test");
+        frame.timestamp = at(secs);
+        frame.activity = Some(CurrentActivity {
+            hostname: "host".to_string(),
+            observed_at: at(secs),
+            app: Some(app.to_string()),
+            title: Some(title.to_string()),
+            window_since: Some(at(secs)),
+            window_duration_secs: 0.0,
+            afk: AfkState::Unknown,
+            afk_since: None,
+            afk_duration_secs: 0.0,
+        });
+        frame
+    }
+
+    fn with_afk(mut frame: NowFrame, afk: AfkState, since: i64) -> NowFrame {
+        let activity = frame.activity.as_mut().unwrap();
+        activity.afk = afk;
+        activity.afk_since = Some(at(since));
+        frame
+    }
+
+    fn with_window_since(mut frame: NowFrame, since: i64) -> NowFrame {
+        frame.activity.as_mut().unwrap().window_since = Some(at(since));
+        frame
+    }
+
+    #[test]
+    fn app_switch_ends_episode_when_observed() {
+        let frames = [
+            frame(0, "code", "a"),
+            frame(60, "code", "a"),
+            frame(120, "chrome", "b"),
+        ];
+        let episodes = segment(&frames, &SegmenterConfig::default());
+        assert_eq!(episodes.len(), 2);
+        assert_eq!(episodes[0].ended_by, EpisodeBoundary::AppChange);
+        assert_eq!(episodes[0].end, at(120));
+        assert_eq!(episodes[0].frame_count, 2);
+        assert_eq!(episodes[1].dominant_app.as_deref(), Some("chrome"));
+        assert_eq!(episodes[1].ended_by, EpisodeBoundary::Open);
+    }
+
+    #[test]
+    fn afk_frame_ends_episode_at_last_active_frame() {
+        let frames = [
+            frame(0, "code", "a"),
+            frame(60, "code", "a"),
+            with_afk(frame(400, "code", "a"), AfkState::Afk, 200),
+            frame(900, "code", "a"),
+        ];
+        let episodes = segment(&frames, &SegmenterConfig::default());
+        assert_eq!(episodes.len(), 2);
+        assert_eq!(episodes[0].ended_by, EpisodeBoundary::Afk);
+        assert_eq!(episodes[0].end, at(60));
+        assert_eq!(episodes[1].start, at(900));
+    }
+
+    #[test]
+    fn sparse_frames_while_not_afk_stay_one_episode() {
+        // long_dwell only fires every 15 minutes during focused work.
+        let frames = [
+            with_afk(frame(0, "code", "a"), AfkState::NotAfk, -60),
+            with_afk(frame(900, "code", "a"), AfkState::NotAfk, -60),
+            with_afk(frame(1800, "code", "a"), AfkState::NotAfk, -60),
+        ];
+        let episodes = segment(&frames, &SegmenterConfig::default());
+        assert_eq!(episodes.len(), 1);
+        assert_eq!(episodes[0].duration_secs, 1800.0);
+        assert_eq!(episodes[0].apps[0].secs, 1800.0);
+    }
+
+    #[test]
+    fn afk_period_between_frames_is_an_idle_gap() {
+        // Not-afk again since 1500: the user was away between the two frames.
+        let frames = [
+            with_afk(frame(0, "code", "a"), AfkState::NotAfk, -60),
+            with_afk(frame(1800, "code", "a"), AfkState::NotAfk, 1500),
+        ];
+        let episodes = segment(&frames, &SegmenterConfig::default());
+        assert_eq!(episodes.len(), 2);
+        assert_eq!(episodes[0].ended_by, EpisodeBoundary::IdleGap);
+        assert_eq!(episodes[0].end, at(0));
+    }
+
+    #[test]
+    fn without_afk_data_a_spanning_window_is_not_idle() {
+        let frames = [
+            frame(0, "code", "a"),
+            with_window_since(frame(900, "code", "a"), 0),
+            with_window_since(frame(1800, "code", "b"), 1700),
+        ];
+        let episodes = segment(&frames, &SegmenterConfig::default());
+        assert_eq!(episodes.len(), 2);
+        assert_eq!(episodes[0].ended_by, EpisodeBoundary::IdleGap);
+        assert_eq!(episodes[0].end, at(900));
+        assert_eq!(episodes[0].frame_count, 2);
+    }
+
+    #[test]
+    fn short_interruption_is_folded_into_the_episode() {
+        let frames = [
+            frame(0, "code", "a"),
+            frame(100, "code", "a"),
+            frame(120, "chrome", "b"),
+            frame(150, "code", "a"),
+            frame(200, "code", "a"),
+        ];
+        let episodes = segment(&frames, &SegmenterConfig::default());
+        assert_eq!(episodes.len(), 1);
+        assert_eq!(episodes[0].frame_count, 5);
+        assert_eq!(episodes[0].dominant_app.as_deref(), Some("code"));
+    }
+
+    #[test]
+    fn title_change_splits_only_when_enabled() {
+        let frames = [frame(0, "code", "a"), frame(60, "code", "b")];
+        assert_eq!(segment(&frames, &SegmenterConfig::default()).len(), 1);
+
+        let config = SegmenterConfig {
+            split_on_title_change: true,
+            ..SegmenterConfig::default()
+        };
+        let episodes = segment(&frames, &config);
+        assert_eq!(episodes.len(), 2);
+        assert_eq!(episodes[0].ended_by, EpisodeBoundary::TitleChange);
+    }
+}
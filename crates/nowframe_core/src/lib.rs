@@ -1,11 +1,121 @@
+mod cache;
+mod episode;
+mod schema;
+mod screen;
+mod store;
+
+use aw_client::{AfkState, CurrentActivity};
+use chrono::{DateTime, Utc};
+use redaction::{Field, Redactor};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use trigger_engine::{ActivitySignal, Decision};
+
+pub use cache::{CacheError, CacheLimits, CacheManager, CachePolicy, CacheStats, GcReport};
+pub use episode::{
+    segment, AppTime, Episode, EpisodeBoundary, KeyElement, SegmenterConfig, MAX_EPISODE_HOURS,
+};
+pub use schema::{migrate, MigrationError, NOWFRAME_JSON_SCHEMA, NOWFRAME_SCHEMA_VERSION};
+pub use screen::ScreenSummary;
+pub use store::{
+    FrameEntry, ListQuery, NowFrameStore, PruneReport, Result, RetentionPolicy, StoreError,
+};
 
+/// Serialized as schema `NOWFRAME_SCHEMA_VERSION`; older JSON is migrated on deserialize.
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(try_from = "Value")]
 pub struct NowFrame {
-    pub timestamp: String,
+    pub schema_version: u32,
+    pub timestamp: DateTime<Utc>,
     pub reason: String,
-    pub aw_info: Option<Value>,
-    pub aw_buckets: Option<Value>,
+    pub activity: Option<CurrentActivity>,
     pub omni_probe: Option<Value>,
+    /// Why the frame was produced; absent for ad-hoc frames built without `explain`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trigger: Option<Decision>,
+    /// Latest parsed screen, when one was captured shortly before the frame.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub screen: Option<ScreenSummary>,
+}
+
+/// The current on-disk shape, deserialized after migration.
+#[derive(Deserialize)]
+struct NowFrameRepr {
+    schema_version: u32,
+    timestamp: DateTime<Utc>,
+    reason: String,
+    #[serde(default)]
+    activity: Option<CurrentActivity>,
+    #[serde(default)]
+    omni_probe: Option<Value>,
+    #[serde(default)]
+    trigger: Option<Decision>,
+    #[serde(default)]
+    screen: Option<ScreenSummary>,
+}
+
+impl NowFrame {
+    /// An empty frame stamped now at the current schema version.
+    pub fn new(reason: impl Into<String>) -> Self {
+        Self {
+            schema_version: NOWFRAME_SCHEMA_VERSION,
+            timestamp: Utc::now(),
+            reason: reason.into(),
+            activity: None,
+            omni_probe: None,
+            trigger: None,
+            screen: None,
+        }
+    }
+
+    /// Applies the privacy rules to every user-content field: the window title and
+    /// the parsed screen texts. Dropped screen texts are removed from the list.
+    pub fn redact(&mut self, redactor: &Redactor) {
+        let app = self.activity.as_ref().and_then(|a| a.app.clone());
+        if let Some(activity) = self.activity.as_mut() {
+            redactor.activity(activity);
+        }
+        if let Some(screen) = self.screen.as_mut() {
+            screen.key_texts = screen
+                .key_texts
+                .iter()
+                .filter_map(|text| redactor.text(text, app.as_deref(), Field::Text))
+                .collect();
+            screen.dominant_text = screen
+                .dominant_text
+                .as_deref()
+                .and_then(|text| redactor.text(text, app.as_deref(), Field::Text));
+        }
+    }
+}
+
+impl TryFrom<Value> for NowFrame {
+    type Error = String;
+
+    fn try_from(value: Value) -> std::result::Result<Self, Self::Error> {
+        let value = migrate(value).map_err(|e| e.to_string())?;
+        let repr: NowFrameRepr = serde_json::from_value(value).map_err(|e| e.to_string())?;
+        Ok(Self {
+            schema_version: repr.schema_version,
+            timestamp: repr.timestamp,
+            reason: repr.reason,
+            activity: repr.activity,
+            omni_probe: repr.omni_probe,
+            trigger: repr.trigger,
+            screen: repr.screen,
+        })
+    }
+}
+
+/// Reduces an AW activity snapshot to the signal the trigger engine scores.
+pub fn activity_signal(activity: &CurrentActivity) -> ActivitySignal {
+    ActivitySignal {
+        app: activity.app.clone(),
+        title: activity.title.clone(),
+        afk: match activity.afk {
+            AfkState::Afk => Some(true),
+            AfkState::NotAfk => Some(false),
+            AfkState::Unknown => None,
+        },
+    }
 }
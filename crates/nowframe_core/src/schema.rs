@@ -0,0 +1,325 @@
+use chrono::{DateTime, NaiveDateTime, SecondsFormat, Utc};
+use serde_json::{Map, Value};
+use thiserror::Error;
+
+/// Version written by this build. Bump it together with a new `migrate_vN` step
+/// and a new schema file under `schema/`.
+pub const NOWFRAME_SCHEMA_VERSION: u32 = 2;
+
+/// JSON Schema (draft 2020-12) for the current NowFrame format.
+pub const NOWFRAME_JSON_SCHEMA: &str = include_str!("../schema/nowframe.v2.schema.json");
+
+#[derive(Debug, Error)]
+pub enum MigrationError {
+    #[error("nowframe must be a JSON object")]
+    NotAnObject,
+    #[error("nowframe schema_version {found} is newer than supported {supported}")]
+    Unsupported { found: u64, supported: u32 },
+    #[error("nowframe v{version}: {message}")]
+    Invalid { version: u64, message: String },
+}
+
+/// Upgrades NowFrame JSON of any known version to the current one.
+///
+/// Frames written before versioning have no `schema_version` and are treated as v1.
+pub fn migrate(value: Value) -> Result<Value, MigrationError> {
+    let Value::Object(mut object) = value else {
+        return Err(MigrationError::NotAnObject);
+    };
+    let mut version = match object.get("schema_version") {
+        None | Some(Value::Null) => 1,
+        Some(v) => v.as_u64().ok_or_else(|| MigrationError::Invalid {
+            version: 0,
+            message: "schema_version is not an integer".to_string(),
+        })?,
+    };
+    if version > NOWFRAME_SCHEMA_VERSION as u64 {
+        return Err(MigrationError::Unsupported {
+            found: version,
+            supported: NOWFRAME_SCHEMA_VERSION,
+        });
+    }
+    while version < NOWFRAME_SCHEMA_VERSION as u64 {
+        object = match version {
+            1 => migrate_v1(object)?,
+            _ => unreachable!("no migration from v{}", version),
+        };
+        version += 1;
+    }
+    Ok(Value::Object(object))
+}
+
+/// v1 -> v2: RFC 3339 UTC timestamp, explicit version, and the baseline
+/// `aw_info` / `aw_buckets` blobs dropped in favour of a null `activity`.
+fn migrate_v1(mut object: Map<String, Value>) -> Result<Map<String, Value>, MigrationError> {
+    let raw = object
+        .get("timestamp")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| MigrationError::Invalid {
+            version: 1,
+            message: "missing timestamp".to_string(),
+        })?;
+    let timestamp = parse_legacy_timestamp(raw).ok_or_else(|| MigrationError::Invalid {
+        version: 1,
+        message: format!("unparseable timestamp {:?}", raw),
+    })?;
+    object.insert(
+        "timestamp".to_string(),
+        Value::String(timestamp.to_rfc3339_opts(SecondsFormat::AutoSi, true)),
+    );
+    object.remove("aw_info");
+    object.remove("aw_buckets");
+    object
+        .entry("reason")
+        .or_insert_with(|| Value::String(String::new()));
+    object.entry("activity").or_insert(Value::Null);
+    object.entry("omni_probe").or_insert(Value::Null);
+    object.insert("schema_version".to_string(), Value::from(2));
+    Ok(object)
+}
+
+fn parse_legacy_timestamp(raw: &str) -> Option<DateTime<Utc>> {
+    if let Ok(ts) = DateTime::parse_from_rfc3339(raw) {
+        return Some(ts.with_timezone(&Utc));
+    }
+    // Naive timestamps were only ever written in UTC.
+    ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f"]
+        .iter()
+        .find_map(|fmt| NaiveDateTime::parse_from_str(raw, fmt).ok())
+        .map(|naive| naive.and_utc())
+}
+
+#[cfg(test)]
+mod tests {
+    use aw_client::{AfkState, CurrentActivity};
+    use serde_json::json;
+    use trigger_engine::{Decision, RuleContribution, Suppression};
+
+    use super::*;
+    use crate::{NowFrame, ScreenSummary};
+
+    /// A frame as the daemon wrote it before `schema_version` existed.
+    fn v1_frame() -> Value {
+        json!({
+            "timestamp": "2025-01-01T12:00:00.123456+08:00",
+            "reason": "manual",
+            "aw_info": { "hostname": "HOST", "version": "v0.13.2", "testing": false },
+            "aw_buckets": { "aw-watcher-window_HOST": { "id": "aw-watcher-window_HOST" } },
+            "omni_probe": { "ok": true, "mode": "mock" }
+        })
+    }
+
+    fn ts(raw: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(raw)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn migrates_a_v1_frame() {
+        let migrated = migrate(v1_frame()).unwrap();
+        assert_eq!(
+            migrated,
+            json!({
+                "schema_version": 2,
+                "timestamp": "2025-01-01T04:00:00.123456Z",
+                "reason": "manual",
+                "activity": null,
+                "omni_probe": { "ok": true, "mode": "mock" }
+            })
+        );
+
+        let frame: NowFrame = serde_json::from_value(v1_frame()).unwrap();
+        assert_eq!(frame.schema_version, NOWFRAME_SCHEMA_VERSION);
+        assert_eq!(frame.timestamp, ts("2025-01-01T04:00:00.123456Z"));
+        assert!(frame.activity.is_none());
+        assert_eq!(
+            frame.omni_probe,
+            Some(json!({ "ok": true, "mode": "mock" }))
+        );
+    }
+
+    #[test]
+    fn naive_v1_timestamps_are_utc() {
+        for raw in ["2025-01-01T12:00:00", "2025-01-01 12:00:00.5"] {
+            let migrated = migrate(json!({ "timestamp": raw, "reason": "r" })).unwrap();
+            let parsed = ts(migrated["timestamp"].as_str().unwrap());
+            assert_eq!(
+                parsed.format("%F %T").to_string(),
+                "2025-01-01 12:00:00",
+                "{raw}"
+            );
+        }
+
+        let err = migrate(json!({ "timestamp": "yesterday" })).unwrap_err();
+        assert!(matches!(err, MigrationError::Invalid { version: 1, .. }));
+        let err = migrate(json!({ "reason": "r" })).unwrap_err();
+        assert!(matches!(err, MigrationError::Invalid { version: 1, .. }));
+    }
+
+    #[test]
+    fn missing_or_null_version_is_v1() {
+        let mut frame = v1_frame();
+        let expected = migrate(frame.clone()).unwrap();
+        frame["schema_version"] = Value::Null;
+        assert_eq!(migrate(frame).unwrap(), expected);
+
+        // A missing reason is filled in so the frame still deserializes.
+        let migrated = migrate(json!({ "timestamp": "2025-01-01T00:00:00Z" })).unwrap();
+        assert_eq!(migrated["reason"], "");
+        assert_eq!(migrated["schema_version"], 2);
+    }
+
+    #[test]
+    fn current_version_is_left_alone() {
+        let frame = json!({
+            "schema_version": 2,
+            "timestamp": "2025-01-01T00:00:00Z",
+            "reason": "r",
+            "aw_info": "kept: only v1 drops it"
+        });
+        assert_eq!(migrate(frame.clone()).unwrap(), frame);
+    }
+
+    #[test]
+    fn rejects_future_and_malformed_versions() {
+        let err = migrate(json!({ "schema_version": 3, "timestamp": "x" })).unwrap_err();
+        assert!(matches!(
+            err,
+            MigrationError::Unsupported {
+                found: 3,
+                supported: 2
+            }
+        ));
+        let frame =
+            json!({ "schema_version": 3, "timestamp": "2025-01-01T00:00:00Z", "reason": "r" });
+        assert!(serde_json::from_value::<NowFrame>(frame).is_err());
+
+        for version in [json!("2"), json!(-1), json!(1.5)] {
+            let err = migrate(json!({ "schema_version": version })).unwrap_err();
+            assert!(matches!(err, MigrationError::Invalid { version: 0, .. }));
+        }
+    }
+
+    #[test]
+    fn rejects_non_objects() {
+        for value in [json!([]), json!("frame"), json!(null), json!(2)] {
+            assert!(matches!(migrate(value), Err(MigrationError::NotAnObject)));
+        }
+    }
+
+    /// Checks object field names against `schema`: no field the schema does not
+    /// declare, and every required field present.
+    fn assert_fields(value: &Value, schema: &Value, defs: &Value, path: &str) {
+        if let Some(name) = schema["$ref"].as_str() {
+            let name = name.trim_start_matches("#/$defs/");
+            return assert_fields(value, &defs[name], defs, path);
+        }
+        if let Some(variants) = schema["oneOf"].as_array() {
+            if value.is_null() {
+                assert!(
+                    variants.contains(&json!({ "type": "null" })),
+                    "{path} is null"
+                );
+                return;
+            }
+            let variant = variants.iter().find(|v| v["type"] != "null").unwrap();
+            return assert_fields(value, variant, defs, path);
+        }
+        match value {
+            Value::Object(object) => {
+                let Some(properties) = schema["properties"].as_object() else {
+                    return;
+                };
+                for (key, field) in object {
+                    let declared = properties.get(key);
+                    assert!(declared.is_some(), "{path}.{key} is not in the schema");
+                    assert_fields(field, declared.unwrap(), defs, &format!("{path}.{key}"));
+                }
+                for required in schema["required"].as_array().into_iter().flatten() {
+                    let required = required.as_str().unwrap();
+                    assert!(
+                        object.contains_key(required),
+                        "{path}.{required} is missing"
+                    );
+                }
+            }
+            Value::Array(items) if schema.get("items").is_some() => {
+                for (i, item) in items.iter().enumerate() {
+                    assert_fields(item, &schema["items"], defs, &format!("{path}[{i}]"));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn written_frames_match_the_schema_and_round_trip() {
+        let schema: Value = serde_json::from_str(NOWFRAME_JSON_SCHEMA).unwrap();
+        assert_eq!(
+            schema["properties"]["schema_version"]["const"],
+            NOWFRAME_SCHEMA_VERSION
+        );
+        let at = ts("2025-01-01T12:00:00Z");
+        let suppressed_by = vec![
+            Suppression::Disarmed { rearm_below: 0.3 },
+            Suppression::Debounce {
+                remaining_secs: 1.0,
+            },
+            Suppression::MinInterval {
+                remaining_secs: 2.0,
+            },
+            Suppression::HourlyBudget { limit: 12 },
+            Suppression::BelowThreshold { fire_above: 0.6 },
+            Suppression::Cooldown {
+                rule: "app_switch".to_string(),
+                remaining_secs: 3.0,
+            },
+        ];
+        let frame = NowFrame {
+            timestamp: at,
+            activity: Some(CurrentActivity {
+                hostname: "HOST".to_string(),
+                observed_at: at,
+                app: Some("Code.exe".to_string()),
+                title: None,
+                window_since: Some(at),
+                window_duration_secs: 12.5,
+                afk: AfkState::NotAfk,
+                afk_since: None,
+                afk_duration_secs: 0.0,
+            }),
+            omni_probe: Some(json!({ "ok": true })),
+            trigger: Some(Decision {
+                fired: false,
+                decided_at: at,
+                score: 0.5,
+                threshold: 0.6,
+                contributions: vec![RuleContribution {
+                    rule: "title_change".to_string(),
+                    weight: 0.5,
+                    explanation: "title changed".to_string(),
+                }],
+                suppressed_by,
+            }),
+            screen: Some(ScreenSummary {
+                frame_id: "frame_20250101_120000_0".to_string(),
+                captured_at: at,
+                element_count: 3,
+                text_count: 2,
+                icon_count: 1,
+                dominant_text: Some("Cargo.toml".to_string()),
+                key_texts: vec!["Cargo.toml".to_string(), "main.rs".to_string()],
+            }),
+            ..NowFrame::new("title_change")
+        };
+
+        let written = serde_json::to_value(&frame).unwrap();
+        assert_fields(&written, &schema, &schema["$defs"], "$");
+        let migrated = migrate(v1_frame()).unwrap();
+        assert_fields(&migrated, &schema, &schema["$defs"], "$");
+
+        let read: NowFrame = serde_json::from_value(written.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&read).unwrap(), written);
+    }
+}
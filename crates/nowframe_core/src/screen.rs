@@ -0,0 +1,102 @@
+use std::fs;
+use std::path::Path;
+
+use chrono::{DateTime, Duration, Utc};
+use omni_client::ParsedElement;
+use serde::{Deserialize, Serialize};
+
+const MAX_KEY_TEXTS: usize = 20;
+const MIN_KEY_TEXT_CHARS: usize = 3;
+const MAX_DOMINANT_TEXT_CHARS: usize = 120;
+
+/// What was on screen when the frame was taken, reduced to the parsed text.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ScreenSummary {
+    pub frame_id: String,
+    pub captured_at: DateTime<Utc>,
+    pub element_count: usize,
+    #[serde(default)]
+    pub text_count: usize,
+    #[serde(default)]
+    pub icon_count: usize,
+    /// Longest text element, cut to 120 characters.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dominant_text: Option<String>,
+    /// Distinct text element contents in parse order, capped at 20.
+    pub key_texts: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct LatestPointer {
+    frame_id: String,
+    ts: String,
+    json_path: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct BundleElements {
+    #[serde(default)]
+    elements: Vec<ParsedElement>,
+}
+
+impl ScreenSummary {
+    /// A parsed screen older than this is not attached to a new NowFrame.
+    pub const DEFAULT_MAX_AGE: Duration = Duration::seconds(120);
+
+    pub fn from_elements(
+        frame_id: &str,
+        captured_at: DateTime<Utc>,
+        elements: &[ParsedElement],
+    ) -> Self {
+        let mut key_texts: Vec<String> = Vec::new();
+        for text in elements.iter().filter_map(|e| e.text()) {
+            let text = text.trim();
+            if text.chars().count() < MIN_KEY_TEXT_CHARS || key_texts.iter().any(|t| t == text) {
+                continue;
+            }
+            key_texts.push(text.to_string());
+            if key_texts.len() >= MAX_KEY_TEXTS {
+                break;
+            }
+        }
+        let dominant_text = elements
+            .iter()
+            .filter(|e| e.is_text())
+            .filter_map(|e| e.text())
+            .max_by_key(|text| text.chars().count())
+            .map(|text| text.chars().take(MAX_DOMINANT_TEXT_CHARS).collect());
+        Self {
+            frame_id: frame_id.to_string(),
+            captured_at,
+            element_count: elements.len(),
+            text_count: elements.iter().filter(|e| e.is_text()).count(),
+            icon_count: elements.iter().filter(|e| e.is_icon()).count(),
+            dominant_text,
+            key_texts,
+        }
+    }
+
+    /// Summarises the bundle `latest.json` in `cache_screens` points at, if it was
+    /// captured within `max_age` of `now`.
+    pub fn from_latest(
+        cache_screens: &Path,
+        max_age: Duration,
+        now: DateTime<Utc>,
+    ) -> Option<Self> {
+        let text = fs::read_to_string(cache_screens.join("latest.json")).ok()?;
+        let latest: LatestPointer = serde_json::from_str(&text).ok()?;
+        let captured_at = DateTime::parse_from_rfc3339(&latest.ts)
+            .ok()?
+            .with_timezone(&Utc);
+        if now - captured_at > max_age {
+            return None;
+        }
+        let bundle = fs::read_to_string(latest.json_path?).ok()?;
+        let bundle: BundleElements = serde_json::from_str(&bundle).ok()?;
+        Some(Self::from_elements(
+            &latest.frame_id,
+            captured_at,
+            &bundle.elements,
+        ))
+    }
+}
@@ -0,0 +1,444 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::NowFrame;
+
+const FILE_PREFIX: &str = "nowframe_";
+const FILE_SUFFIX: &str = ".json";
+const ID_TIME_FORMAT: &str = "%Y%m%d_%H%M%S";
+
+#[derive(Debug, Error)]
+pub enum StoreError {
+    #[error("nowframe io failed: {path}: {source}")]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    #[error("nowframe json invalid: {path}: {source}")]
+    Json {
+        path: PathBuf,
+        #[source]
+        source: serde_json::Error,
+    },
+    #[error("invalid nowframe id: {0}")]
+    InvalidId(String),
+    #[error("nowframe not found: {0}")]
+    NotFound(String),
+}
+
+impl StoreError {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            StoreError::Io { .. } => "io",
+            StoreError::Json { .. } => "invalid_json",
+            StoreError::InvalidId(_) => "invalid_request",
+            StoreError::NotFound(_) => "not_found",
+        }
+    }
+
+    fn io(path: &Path, source: io::Error) -> Self {
+        StoreError::Io {
+            path: path.to_path_buf(),
+            source,
+        }
+    }
+}
+
+pub type Result<T> = std::result::Result<T, StoreError>;
+
+/// Rolling retention for stored frames; the newest frame is always kept.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetentionPolicy {
+    pub max_age_secs: u64,
+    pub max_total_bytes: u64,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            max_age_secs: 24 * 60 * 60,
+            max_total_bytes: 256 * 1024 * 1024,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FrameEntry {
+    pub id: String,
+    pub timestamp: DateTime<Utc>,
+    pub size_bytes: u64,
+    #[serde(skip)]
+    pub path: PathBuf,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ListQuery {
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct PruneReport {
+    pub removed: usize,
+    pub removed_bytes: u64,
+    pub remaining: usize,
+    pub remaining_bytes: u64,
+}
+
+/// One JSON file per frame under `data_nowframes`, named by its timestamp so a
+/// directory listing is the index.
+pub struct NowFrameStore {
+    dir: PathBuf,
+    retention: RetentionPolicy,
+}
+
+impl NowFrameStore {
+    pub fn new(dir: impl Into<PathBuf>, retention: RetentionPolicy) -> Self {
+        Self {
+            dir: dir.into(),
+            retention,
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn retention(&self) -> &RetentionPolicy {
+        &self.retention
+    }
+
+    /// Writes the frame, then applies retention. Returns the stored entry.
+    pub fn save(&self, frame: &NowFrame) -> Result<FrameEntry> {
+        fs::create_dir_all(&self.dir).map_err(|e| StoreError::io(&self.dir, e))?;
+        let timestamp = frame.timestamp;
+        let base = timestamp.format("%Y%m%d_%H%M%S_%3f").to_string();
+        let mut id = base.clone();
+        let mut n = 1;
+        while self.path_for(&id).exists() {
+            id = format!("{}_{}", base, n);
+            n += 1;
+        }
+
+        let path = self.path_for(&id);
+        let text = serde_json::to_string_pretty(frame).map_err(|source| StoreError::Json {
+            path: path.clone(),
+            source,
+        })?;
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, &text).map_err(|e| StoreError::io(&tmp, e))?;
+        fs::rename(&tmp, &path).map_err(|e| StoreError::io(&path, e))?;
+
+        self.prune(Utc::now())?;
+        Ok(FrameEntry {
+            id,
+            timestamp: parse_id_time(&base).unwrap_or(timestamp),
+            size_bytes: text.len() as u64,
+            path,
+        })
+    }
+
+    /// Entries matching `query`, newest first.
+    pub fn list(&self, query: &ListQuery) -> Result<Vec<FrameEntry>> {
+        let mut entries: Vec<FrameEntry> = self
+            .scan()?
+            .into_iter()
+            .filter(|e| query.since.is_none_or(|since| e.timestamp >= since))
+            .filter(|e| query.until.is_none_or(|until| e.timestamp <= until))
+            .collect();
+        entries.reverse();
+        if let Some(limit) = query.limit {
+            entries.truncate(limit);
+        }
+        Ok(entries)
+    }
+
+    pub fn get(&self, id: &str) -> Result<NowFrame> {
+        if !is_valid_id(id) {
+            return Err(StoreError::InvalidId(id.to_string()));
+        }
+        let path = self.path_for(id);
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                return Err(StoreError::NotFound(id.to_string()))
+            }
+            Err(err) => return Err(StoreError::io(&path, err)),
+        };
+        serde_json::from_str(&text).map_err(|source| StoreError::Json { path, source })
+    }
+
+    pub fn latest(&self) -> Result<Option<(FrameEntry, NowFrame)>> {
+        let Some(entry) = self.scan()?.pop() else {
+            return Ok(None);
+        };
+        let frame = self.get(&entry.id)?;
+        Ok(Some((entry, frame)))
+    }
+
+    /// Deletes frames older than `max_age_secs`, then the oldest frames until the
+    /// directory fits in `max_total_bytes`.
+    pub fn prune(&self, now: DateTime<Utc>) -> Result<PruneReport> {
+        let mut entries = self.scan()?;
+        let newest = entries.pop();
+        // An age too large to subtract from `now` is no age limit at all.
+        let cutoff = i64::try_from(self.retention.max_age_secs)
+            .ok()
+            .and_then(Duration::try_seconds)
+            .and_then(|age| now.checked_sub_signed(age));
+        let mut total: u64 = entries.iter().map(|e| e.size_bytes).sum::<u64>()
+            + newest.as_ref().map(|e| e.size_bytes).unwrap_or(0);
+        let mut report = PruneReport::default();
+
+        for entry in &entries {
+            let expired = cutoff.is_some_and(|cutoff| entry.timestamp < cutoff);
+            if !expired && total <= self.retention.max_total_bytes {
+                break;
+            }
+            match fs::remove_file(&entry.path) {
+                Ok(()) => {}
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+                Err(err) => return Err(StoreError::io(&entry.path, err)),
+            }
+            total -= entry.size_bytes;
+            report.removed += 1;
+            report.removed_bytes += entry.size_bytes;
+        }
+
+        report.remaining = entries.len() - report.removed + usize::from(newest.is_some());
+        report.remaining_bytes = total;
+        Ok(report)
+    }
+
+    fn path_for(&self, id: &str) -> PathBuf {
+        self.dir
+            .join(format!("{}{}{}", FILE_PREFIX, id, FILE_SUFFIX))
+    }
+
+    /// All stored entries, oldest first.
+    fn scan(&self) -> Result<Vec<FrameEntry>> {
+        let read_dir = match fs::read_dir(&self.dir) {
+            Ok(read_dir) => read_dir,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(StoreError::io(&self.dir, err)),
+        };
+        let mut entries = Vec::new();
+        for item in read_dir {
+            let item = item.map_err(|e| StoreError::io(&self.dir, e))?;
+            let name = item.file_name();
+            let Some(id) = name
+                .to_str()
+                .and_then(|n| n.strip_prefix(FILE_PREFIX))
+                .and_then(|n| n.strip_suffix(FILE_SUFFIX))
+            else {
+                continue;
+            };
+            let Some(timestamp) = parse_id_time(id) else {
+                continue;
+            };
+            let size_bytes = item.metadata().map(|m| m.len()).unwrap_or(0);
+            entries.push(FrameEntry {
+                id: id.to_string(),
+                timestamp,
+                size_bytes,
+                path: item.path(),
+            });
+        }
+        entries.sort_by(|a, b| a.timestamp.cmp(&b.timestamp).then_with(|| a.id.cmp(&b.id)));
+        Ok(entries)
+    }
+}
+
+/// Ids look like `20250101_120000_123` with an optional `_N` collision suffix.
+fn parse_id_time(id: &str) -> Option<DateTime<Utc>> {
+    let seconds = NaiveDateTime::parse_from_str(id.get(..15)?, ID_TIME_FORMAT).ok()?;
+    let millis: i64 = id.get(16..19)?.parse().ok()?;
+    Some(seconds.and_utc() + Duration::milliseconds(millis))
+}
+
+fn is_valid_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.chars().all(|c| c.is_ascii_digit() || c == '_')
+        && parse_id_time(id).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use tempfile::TempDir;
+
+    use super::*;
+
+    /// A store that keeps everything, so `save` never prunes what a test wrote.
+    fn store() -> (TempDir, NowFrameStore) {
+        let dir = tempfile::tempdir().unwrap();
+        let store = NowFrameStore::new(dir.path(), retention(u64::MAX, u64::MAX));
+        (dir, store)
+    }
+
+    fn retention(max_age_secs: u64, max_total_bytes: u64) -> RetentionPolicy {
+        RetentionPolicy {
+            max_age_secs,
+            max_total_bytes,
+        }
+    }
+
+    fn t0() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, 17, 4, 0, 0).unwrap()
+    }
+
+    /// Saves one frame per offset (in minutes after `t0`) and returns the ids.
+    fn save_at(store: &NowFrameStore, minutes: &[i64]) -> Vec<String> {
+        minutes
+            .iter()
+            .map(|m| {
+                let frame = NowFrame {
+                    timestamp: t0() + Duration::minutes(*m),
+                    ..NowFrame::new("test")
+                };
+                store.save(&frame).unwrap().id
+            })
+            .collect()
+    }
+
+    fn ids(store: &NowFrameStore, query: &ListQuery) -> Vec<String> {
+        store
+            .list(query)
+            .unwrap()
+            .into_iter()
+            .map(|e| e.id)
+            .collect()
+    }
+
+    #[test]
+    fn prunes_by_age_and_keeps_the_newest() {
+        let (dir, store) = store();
+        let saved = save_at(&store, &[0, 10, 20]);
+
+        // At t0+25m with a 20 minute limit only t0 is expired.
+        let pruner = NowFrameStore::new(dir.path(), retention(1200, u64::MAX));
+        let report = pruner.prune(t0() + Duration::minutes(25)).unwrap();
+        assert_eq!(report.removed, 1);
+        assert_eq!(report.remaining, 2);
+        assert_eq!(
+            ids(&store, &ListQuery::default()),
+            [saved[2].as_str(), saved[1].as_str()]
+        );
+
+        // Long after every frame expired, the newest one still stays.
+        let report = pruner.prune(t0() + Duration::days(1)).unwrap();
+        assert_eq!(report.removed, 1);
+        assert_eq!(ids(&store, &ListQuery::default()), [saved[2].as_str()]);
+    }
+
+    #[test]
+    fn prunes_oldest_first_to_fit_the_byte_budget() {
+        let (dir, store) = store();
+        let saved = save_at(&store, &[0, 1, 2]);
+        let sizes: Vec<u64> = store
+            .list(&ListQuery::default())
+            .unwrap()
+            .iter()
+            .map(|e| e.size_bytes)
+            .collect();
+
+        let budget = sizes[0] + sizes[1];
+        let report = NowFrameStore::new(dir.path(), retention(u64::MAX, budget))
+            .prune(t0())
+            .unwrap();
+        assert_eq!(report.removed, 1);
+        assert_eq!(report.remaining_bytes, budget);
+        assert_eq!(
+            ids(&store, &ListQuery::default()),
+            [saved[2].as_str(), saved[1].as_str()]
+        );
+
+        // A budget smaller than one frame still keeps the newest.
+        let report = NowFrameStore::new(dir.path(), retention(u64::MAX, 1))
+            .prune(t0())
+            .unwrap();
+        assert_eq!(report.remaining, 1);
+        assert_eq!(ids(&store, &ListQuery::default()), [saved[2].as_str()]);
+    }
+
+    #[test]
+    fn huge_max_age_is_no_age_limit() {
+        let (dir, store) = store();
+        save_at(&store, &[0, 1]);
+        for max_age_secs in [i64::MAX as u64, u64::MAX] {
+            let report = NowFrameStore::new(dir.path(), retention(max_age_secs, u64::MAX))
+                .prune(Utc::now())
+                .unwrap();
+            assert_eq!(report.removed, 0);
+            assert_eq!(report.remaining, 2);
+        }
+    }
+
+    #[test]
+    fn same_timestamp_gets_a_collision_suffix() {
+        let (_dir, store) = store();
+        let saved = save_at(&store, &[0, 0, 0]);
+
+        assert_eq!(
+            saved,
+            [
+                "20261017_040000_000",
+                "20261017_040000_000_1",
+                "20261017_040000_000_2"
+            ]
+        );
+        assert_eq!(store.get(saved[2].as_str()).unwrap().timestamp, t0());
+        assert_eq!(
+            ids(&store, &ListQuery::default()),
+            [saved[2].as_str(), saved[1].as_str(), saved[0].as_str()]
+        );
+    }
+
+    #[test]
+    fn list_filters_newest_first_and_limits() {
+        let (_dir, store) = store();
+        let saved = save_at(&store, &[0, 1, 2, 3]);
+
+        let query = ListQuery {
+            since: Some(t0() + Duration::minutes(1)),
+            until: Some(t0() + Duration::minutes(2)),
+            limit: None,
+        };
+        assert_eq!(ids(&store, &query), [saved[2].as_str(), saved[1].as_str()]);
+
+        let query = ListQuery {
+            limit: Some(2),
+            ..ListQuery::default()
+        };
+        assert_eq!(ids(&store, &query), [saved[3].as_str(), saved[2].as_str()]);
+    }
+
+    #[test]
+    fn get_rejects_invalid_and_path_like_ids() {
+        let (dir, store) = store();
+        save_at(&store, &[0]);
+        fs::write(dir.path().join("secret.json"), "{}").unwrap();
+
+        for id in [
+            "",
+            "latest",
+            "../secret",
+            "20261017_040000_000/../../secret",
+            "20261017_040000_000.json",
+            "99999999_999999_999",
+        ] {
+            let err = store.get(id).unwrap_err();
+            assert!(matches!(err, StoreError::InvalidId(_)), "{id}: {err}");
+        }
+        let err = store.get("20261017_040000_000_7").unwrap_err();
+        assert!(matches!(err, StoreError::NotFound(_)), "{err}");
+    }
+}
@@ -1,26 +1,82 @@
-use anyhow::{anyhow, Context, Result};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use http_transport::{Retry, Transport, TransportConfig};
 use serde_json::{json, Value};
 
+mod parse;
+mod status;
+
+pub use http_transport::{ClientError, Result};
+pub use parse::{ElementKind, NormBox, ParseResponse, ParsedElement, PixelBox};
+pub use status::{ProbeReason, SidecarStatus};
+
 #[derive(Clone, Debug)]
 pub struct OmniClient {
-    base_url: String,
+    transport: Transport,
 }
 
 impl OmniClient {
     pub fn new(base_url: impl Into<String>) -> Self {
+        Self::with_config(base_url, TransportConfig::default())
+    }
+
+    pub fn with_config(base_url: impl Into<String>, config: TransportConfig) -> Self {
         Self {
-            base_url: base_url.into(),
+            transport: Transport::new(base_url, config),
         }
     }
 
     pub fn probe(&self) -> Result<Value> {
         match self.get_json("/probe") {
-            Ok(value) => Ok(value),
-            Err(_) => self.get_json("/probe/"),
+            Err(err) if is_route_miss(&err) => self.get_json("/probe/"),
+            other => other,
+        }
+    }
+
+    pub fn status(&self) -> Result<SidecarStatus> {
+        self.probe().map(|probe| SidecarStatus::from_probe(&probe))
+    }
+
+    /// Probes first and only posts `/parse` once the sidecar reports `ready`. While it is
+    /// unreachable or not ready, retries with backoff until `wait` has elapsed; a zero
+    /// `wait` checks readiness exactly once.
+    pub fn parse_when_ready(
+        &self,
+        base64_image: &str,
+        parse_options: Option<&Value>,
+        wait: Duration,
+    ) -> Result<ParseResponse> {
+        let deadline = Instant::now() + wait;
+        let mut attempt = 0;
+        loop {
+            let err = match self.status() {
+                Ok(status) if status.ready => match self.parse(base64_image, parse_options) {
+                    Err(err @ ClientError::NotReady { .. }) => err,
+                    other => return other,
+                },
+                Ok(status) => ClientError::NotReady {
+                    reason: status.reason.as_str().to_string(),
+                },
+                Err(err) if err.is_retryable() => err,
+                Err(err) => return Err(err),
+            };
+
+            let delay = self.transport.config().backoff(attempt);
+            let now = Instant::now();
+            if now + delay > deadline {
+                return Err(err);
+            }
+            thread::sleep(delay);
+            attempt += 1;
         }
     }
 
-    pub fn parse(&self, base64_image: &str, parse_options: Option<&Value>) -> Result<Value> {
+    pub fn parse(
+        &self,
+        base64_image: &str,
+        parse_options: Option<&Value>,
+    ) -> Result<ParseResponse> {
         let mut payload = json!({ "base64_image": base64_image });
         if let Some(options) = parse_options {
             if !options.is_null() {
@@ -28,33 +84,32 @@ impl OmniClient {
             }
         }
 
-        match self.post_json("/parse", &payload) {
-            Ok(value) => Ok(value),
-            Err(_) => self.post_json("/parse/", &payload),
-        }
+        let result = match self.post_json("/parse", &payload) {
+            Err(err) if is_route_miss(&err) => self.post_json("/parse/", &payload),
+            other => other,
+        };
+        let value = result?;
+        serde_json::from_value(value).map_err(ClientError::invalid_json)
     }
 
     fn get_json(&self, path: &str) -> Result<Value> {
-        let url = format!("{}{}", self.base_url.trim_end_matches('/'), path);
-        let response = ureq::get(&url)
-            .call()
-            .with_context(|| format!("GET {} failed", url))?;
-        let text = response
-            .into_string()
-            .map_err(|e| anyhow!("read response body failed: {}", e))?;
-        let value = serde_json::from_str(&text).map_err(|e| anyhow!("parse json failed: {}", e))?;
-        Ok(value)
+        self.transport.get_json(path, &[])
     }
 
+    // A parse is expensive and deterministic: a read timeout or 500 would only repeat,
+    // so it is re-sent only when the connection was never made.
     fn post_json(&self, path: &str, payload: &Value) -> Result<Value> {
-        let url = format!("{}{}", self.base_url.trim_end_matches('/'), path);
-        let response = ureq::post(&url)
-            .send_json(payload.clone())
-            .with_context(|| format!("POST {} failed", url))?;
-        let text = response
-            .into_string()
-            .map_err(|e| anyhow!("read response body failed: {}", e))?;
-        let value = serde_json::from_str(&text).map_err(|e| anyhow!("parse json failed: {}", e))?;
-        Ok(value)
+        self.transport
+            .post_json(path, &[], payload, Retry::ConnectOnly)
     }
 }
+
+fn is_route_miss(err: &ClientError) -> bool {
+    matches!(
+        err,
+        ClientError::HttpStatus {
+            code: 404 | 405,
+            ..
+        }
+    )
+}
@@ -0,0 +1,499 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ElementKind {
+    Text,
+    Icon,
+    Other,
+}
+
+/// Bounding box as fractions of the image size.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NormBox {
+    pub x1: f32,
+    pub y1: f32,
+    pub x2: f32,
+    pub y2: f32,
+}
+
+/// Bounding box in image pixels, clamped to the image and at least 1x1.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PixelBox {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(from = "RawElement")]
+pub struct ParsedElement {
+    pub kind: ElementKind,
+    pub content: Option<String>,
+    pub bbox_norm: Option<NormBox>,
+    pub bbox_px: Option<PixelBox>,
+    pub interactivity: Option<bool>,
+    pub source: Option<String>,
+    pub confidence: Option<f32>,
+    #[serde(skip)]
+    raw_bbox: Option<RawBox>,
+}
+
+/// A sidecar `[x1, y1, x2, y2]` box before the image size is known.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum RawBox {
+    Normalized([f32; 4]),
+    Pixels([f32; 4]),
+    /// Plain `bbox`, whose unit has to be inferred from its values.
+    Unlabelled([f32; 4]),
+}
+
+impl ParsedElement {
+    pub fn is_text(&self) -> bool {
+        self.kind == ElementKind::Text
+    }
+
+    pub fn is_icon(&self) -> bool {
+        self.kind == ElementKind::Icon
+    }
+
+    pub fn text(&self) -> Option<&str> {
+        self.content
+            .as_deref()
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+    }
+
+    /// Fills `bbox_norm` and `bbox_px` from the sidecar box. Explicit
+    /// `bbox_normalized` / `bbox_px` keys are taken at their word; a plain `bbox`
+    /// is normalised (OmniParser default) when every value lies in 0..=1.
+    pub fn resolve_bbox(&mut self, width: u32, height: u32) {
+        if width == 0 || height == 0 {
+            return;
+        }
+        let raw = match self.raw_bbox {
+            Some(raw) => raw,
+            None => match (self.bbox_norm, self.bbox_px) {
+                (Some(n), _) => RawBox::Normalized([n.x1, n.y1, n.x2, n.y2]),
+                (None, Some(p)) => RawBox::Pixels([
+                    p.x as f32,
+                    p.y as f32,
+                    p.x.saturating_add(p.width) as f32,
+                    p.y.saturating_add(p.height) as f32,
+                ]),
+                (None, None) => return,
+            },
+        };
+
+        let w = width as f32;
+        let h = height as f32;
+        let scale = |b: [f32; 4]| [b[0] * w, b[1] * h, b[2] * w, b[3] * h];
+        let [x1, y1, x2, y2] = match raw {
+            RawBox::Normalized(b) => scale(b),
+            RawBox::Pixels(b) => b,
+            RawBox::Unlabelled(b) if b.iter().all(|v| (0.0..=1.0).contains(v)) => scale(b),
+            RawBox::Unlabelled(b) => b,
+        };
+
+        let px1 = x1.round().clamp(0.0, (width - 1) as f32) as u32;
+        let py1 = y1.round().clamp(0.0, (height - 1) as f32) as u32;
+        let px2 = x2.round().clamp(0.0, w) as u32;
+        let py2 = y2.round().clamp(0.0, h) as u32;
+
+        self.bbox_px = Some(PixelBox {
+            x: px1,
+            y: py1,
+            width: px2.saturating_sub(px1).max(1),
+            height: py2.saturating_sub(py1).max(1),
+        });
+        self.bbox_norm = Some(NormBox {
+            x1: (x1 / w).clamp(0.0, 1.0),
+            y1: (y1 / h).clamp(0.0, 1.0),
+            x2: (x2 / w).clamp(0.0, 1.0),
+            y2: (y2 / h).clamp(0.0, 1.0),
+        });
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(from = "RawParseResponse")]
+pub struct ParseResponse {
+    pub ok: bool,
+    pub latency_ms: i64,
+    pub elements: Vec<ParsedElement>,
+    pub som_image_base64: Option<String>,
+}
+
+impl ParseResponse {
+    pub fn resolve_bboxes(&mut self, width: u32, height: u32) {
+        for element in &mut self.elements {
+            element.resolve_bbox(width, height);
+        }
+    }
+
+    pub fn has_text(&self) -> bool {
+        self.elements.iter().any(ParsedElement::is_text)
+    }
+
+    pub fn has_icon(&self) -> bool {
+        self.elements.iter().any(ParsedElement::is_icon)
+    }
+}
+
+// Mock and real sidecars disagree on field names and on whether boxes are normalised;
+// accept every known spelling and also the typed form written back to disk.
+#[derive(Deserialize)]
+struct RawElement {
+    #[serde(default)]
+    kind: Option<ElementKind>,
+    #[serde(default, rename = "type")]
+    type_name: Option<String>,
+    #[serde(default)]
+    category: Option<String>,
+    #[serde(default)]
+    content: Option<Value>,
+    #[serde(default)]
+    text: Option<String>,
+    #[serde(default)]
+    bbox: Option<Vec<Value>>,
+    #[serde(default)]
+    bbox_normalized: Option<Vec<Value>>,
+    #[serde(default)]
+    bbox_norm: Option<NormBox>,
+    /// Either the typed `{x, y, width, height}` or a sidecar `[x1, y1, x2, y2]`.
+    #[serde(default)]
+    bbox_px: Option<Value>,
+    #[serde(default)]
+    interactivity: Option<Value>,
+    #[serde(default)]
+    interactive: Option<bool>,
+    #[serde(default)]
+    source: Option<String>,
+    #[serde(default)]
+    confidence: Option<f32>,
+    #[serde(default)]
+    score: Option<f32>,
+}
+
+impl From<RawElement> for ParsedElement {
+    fn from(raw: RawElement) -> Self {
+        let content = match raw.content {
+            Some(Value::String(s)) => Some(s),
+            Some(Value::Null) | None => raw.text,
+            Some(other) => Some(other.to_string()),
+        };
+        let has_content = content.as_deref().map(|s| !s.trim().is_empty()) == Some(true);
+        let kind = raw
+            .kind
+            .or_else(|| raw.type_name.as_deref().and_then(kind_from_label))
+            .or_else(|| raw.category.as_deref().and_then(kind_from_label))
+            .unwrap_or(if has_content {
+                ElementKind::Text
+            } else {
+                ElementKind::Other
+            });
+
+        let bbox_px = raw
+            .bbox_px
+            .as_ref()
+            .and_then(|v| serde_json::from_value::<PixelBox>(v.clone()).ok());
+        let pixel_list = match &raw.bbox_px {
+            Some(Value::Array(values)) => box_values(values).map(RawBox::Pixels),
+            _ => None,
+        };
+        let raw_bbox = raw
+            .bbox_normalized
+            .as_deref()
+            .and_then(box_values)
+            .map(RawBox::Normalized)
+            .or(pixel_list)
+            .or_else(|| {
+                raw.bbox
+                    .as_deref()
+                    .and_then(box_values)
+                    .map(RawBox::Unlabelled)
+            });
+
+        let interactivity = match raw.interactivity {
+            Some(Value::Bool(b)) => Some(b),
+            Some(Value::Number(n)) => n.as_f64().map(|v| v != 0.0),
+            _ => raw.interactive,
+        };
+
+        ParsedElement {
+            kind,
+            content,
+            bbox_norm: raw.bbox_norm,
+            bbox_px,
+            interactivity,
+            source: raw.source,
+            confidence: raw.confidence.or(raw.score),
+            raw_bbox,
+        }
+    }
+}
+
+fn box_values(values: &[Value]) -> Option<[f32; 4]> {
+    if values.len() < 4 {
+        return None;
+    }
+    let mut out = [0.0f32; 4];
+    for (slot, value) in out.iter_mut().zip(values.iter()) {
+        *slot = value.as_f64()? as f32;
+    }
+    Some(out)
+}
+
+fn kind_from_label(label: &str) -> Option<ElementKind> {
+    let lower = label.to_ascii_lowercase();
+    if lower.contains("text") {
+        Some(ElementKind::Text)
+    } else if lower.contains("icon") {
+        Some(ElementKind::Icon)
+    } else {
+        None
+    }
+}
+
+#[derive(Deserialize)]
+struct RawParseResponse {
+    #[serde(default)]
+    ok: Option<bool>,
+    #[serde(default)]
+    latency_ms: Option<f64>,
+    #[serde(default)]
+    latency: Option<f64>,
+    #[serde(default)]
+    parsed_content_list: Option<Vec<ParsedElement>>,
+    #[serde(default)]
+    elements: Option<Vec<ParsedElement>>,
+    #[serde(default)]
+    som_image_base64: Option<String>,
+}
+
+impl From<RawParseResponse> for ParseResponse {
+    fn from(raw: RawParseResponse) -> Self {
+        let latency_ms = raw
+            .latency_ms
+            .or_else(|| raw.latency.map(|secs| secs * 1000.0))
+            .unwrap_or(0.0)
+            .round() as i64;
+        ParseResponse {
+            ok: raw.ok.unwrap_or(true),
+            latency_ms,
+            elements: raw.parsed_content_list.or(raw.elements).unwrap_or_default(),
+            som_image_base64: raw.som_image_base64.filter(|s| !s.is_empty()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `POST /parse` from `sidecar/omni_sidecar_mock.py`.
+    const MOCK: &str = r#"{
+        "latency": 0.0004,
+        "latency_ms": 0,
+        "parsed_content_list": [
+            {"type": "text", "content": "mock text", "bbox_normalized": [0.1, 0.1, 0.3, 0.15], "interactivity": false, "score": 0.5},
+            {"type": "icon", "content": "mock-icon", "bbox_normalized": [0.9, 0.02, 0.93, 0.06], "interactivity": true, "score": 0.2}
+        ],
+        "som_image_base64": ""
+    }"#;
+
+    /// `POST /parse` from the real sidecar: OmniParser's own element dicts.
+    const REAL: &str = r#"{
+        "ok": true,
+        "latency": 1.234,
+        "latency_ms": 1234,
+        "parsed_content_list": [
+            {"type": "text", "bbox": [0.1, 0.1, 0.3, 0.15], "interactivity": false, "content": "mock text", "source": "box_ocr_content_ocr"},
+            {"type": "icon", "bbox": [0.9, 0.02, 0.93, 0.06], "interactivity": true, "content": "mock-icon ", "source": "box_yolo_content_yolo"}
+        ],
+        "som_image_base64": "iVBORw0KGgo="
+    }"#;
+
+    fn parse(text: &str) -> ParseResponse {
+        let mut response: ParseResponse = serde_json::from_str(text).unwrap();
+        response.resolve_bboxes(1000, 800);
+        response
+    }
+
+    /// The fields consumers read: kind, text, both boxes and interactivity.
+    fn summary(response: &ParseResponse) -> Vec<Value> {
+        response
+            .elements
+            .iter()
+            .map(|e| serde_json::json!([e.kind, e.text(), e.bbox_px, e.bbox_norm, e.interactivity]))
+            .collect()
+    }
+
+    fn element(json: &str) -> ParsedElement {
+        let mut element: ParsedElement = serde_json::from_str(json).unwrap();
+        element.resolve_bbox(1000, 800);
+        element
+    }
+
+    #[test]
+    fn mock_and_real_responses_parse_alike() {
+        let mock = parse(MOCK);
+        let real = parse(REAL);
+        assert_eq!(summary(&mock), summary(&real));
+
+        let text = &real.elements[0];
+        assert!(text.is_text());
+        assert_eq!(
+            text.bbox_px,
+            Some(PixelBox {
+                x: 100,
+                y: 80,
+                width: 200,
+                height: 40
+            })
+        );
+        assert_eq!(text.source.as_deref(), Some("box_ocr_content_ocr"));
+        assert!(real.elements[1].is_icon());
+        assert!(real.has_text() && real.has_icon());
+
+        assert!(mock.ok);
+        assert_eq!(mock.elements[0].confidence, Some(0.5));
+        assert_eq!(real.latency_ms, 1234);
+        assert!(mock.som_image_base64.is_none());
+        assert!(real.som_image_base64.is_some());
+    }
+
+    #[test]
+    fn alternative_spellings_and_missing_fields() {
+        let response = parse(
+            r#"{
+                "latency": 0.25,
+                "elements": [
+                    {"category": "TextBox", "text": "Save", "interactive": true, "confidence": 0.9},
+                    {"kind": "icon", "content": {"label": "gear"}, "interactivity": 1},
+                    {"content": "  "},
+                    {"content": "untyped"},
+                    {"type": "text", "content": "extra", "bbox": [1, 2], "colour": "red", "nested": {"a": [1]}}
+                ],
+                "unknown_top_level": 1
+            }"#,
+        );
+        assert_eq!(response.latency_ms, 250);
+        assert!(response.ok);
+
+        let [save, gear, blank, untyped, extra] = &response.elements[..] else {
+            panic!("expected 5 elements");
+        };
+        assert_eq!((save.kind, save.text()), (ElementKind::Text, Some("Save")));
+        assert_eq!(
+            (save.interactivity, save.confidence),
+            (Some(true), Some(0.9))
+        );
+        assert_eq!(gear.kind, ElementKind::Icon);
+        assert_eq!(gear.text(), Some(r#"{"label":"gear"}"#));
+        assert_eq!(gear.interactivity, Some(true));
+        assert_eq!((blank.kind, blank.text()), (ElementKind::Other, None));
+        assert_eq!(untyped.kind, ElementKind::Text);
+        // A box with too few values is ignored rather than guessed at.
+        assert!(extra.bbox_px.is_none() && extra.bbox_norm.is_none());
+
+        let empty = parse(r#"{"ok": false}"#);
+        assert!(!empty.ok);
+        assert!(empty.elements.is_empty());
+        assert_eq!(empty.latency_ms, 0);
+    }
+
+    #[test]
+    fn explicit_box_keys_win_over_guessing() {
+        // Pixel boxes that happen to lie within 0..=1.5 stay pixels.
+        let tiny = element(r#"{"content": "x", "bbox_px": [0, 0, 1.5, 1.5]}"#);
+        assert_eq!(
+            tiny.bbox_px,
+            Some(PixelBox {
+                x: 0,
+                y: 0,
+                width: 2,
+                height: 2
+            })
+        );
+
+        let typed =
+            element(r#"{"content": "x", "bbox_px": {"x": 1, "y": 1, "width": 1, "height": 1}}"#);
+        assert_eq!(
+            typed.bbox_px,
+            Some(PixelBox {
+                x: 1,
+                y: 1,
+                width: 1,
+                height: 1
+            })
+        );
+
+        let normalized = element(r#"{"content": "x", "bbox_normalized": [0.5, 0.5, 1.0, 1.0]}"#);
+        assert_eq!(
+            normalized.bbox_px,
+            Some(PixelBox {
+                x: 500,
+                y: 400,
+                width: 500,
+                height: 400
+            })
+        );
+
+        // A plain `bbox` is only normalised when every value is a fraction.
+        let guessed = element(r#"{"content": "x", "bbox": [0.5, 0.5, 1.0, 1.0]}"#);
+        assert_eq!(guessed.bbox_px, normalized.bbox_px);
+        let pixels = element(r#"{"content": "x", "bbox": [0, 0, 1.5, 1.5]}"#);
+        assert_eq!(pixels.bbox_px, tiny.bbox_px);
+    }
+
+    #[test]
+    fn boxes_are_clamped_without_overflow() {
+        let huge = element(&format!(
+            r#"{{"content": "x", "bbox_px": {{"x": {max}, "y": 10, "width": {max}, "height": 10}}}}"#,
+            max = u32::MAX
+        ));
+        assert_eq!(
+            huge.bbox_px,
+            Some(PixelBox {
+                x: 999,
+                y: 10,
+                width: 1,
+                height: 10
+            })
+        );
+
+        let outside = element(r#"{"content": "x", "bbox": [-50, -50, 5000, 5000]}"#);
+        assert_eq!(
+            outside.bbox_px,
+            Some(PixelBox {
+                x: 0,
+                y: 0,
+                width: 1000,
+                height: 800
+            })
+        );
+        assert_eq!(
+            outside.bbox_norm,
+            Some(NormBox {
+                x1: 0.0,
+                y1: 0.0,
+                x2: 1.0,
+                y2: 1.0
+            })
+        );
+    }
+
+    #[test]
+    fn written_elements_read_back_unchanged() {
+        let real = parse(REAL);
+        let text = serde_json::to_string(&real.elements).unwrap();
+        let mut back: Vec<ParsedElement> = serde_json::from_str(&text).unwrap();
+        for element in &mut back {
+            element.resolve_bbox(1000, 800);
+        }
+        assert_eq!(serde_json::to_string(&back).unwrap(), text);
+    }
+}
@@ -0,0 +1,172 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProbeReason {
+    Ready,
+    ImportsMissing,
+    RepoMissing,
+    WeightsMissing,
+    Unknown,
+}
+
+impl ProbeReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProbeReason::Ready => "ready",
+            ProbeReason::ImportsMissing => "imports_missing",
+            ProbeReason::RepoMissing => "repo_missing",
+            ProbeReason::WeightsMissing => "weights_missing",
+            ProbeReason::Unknown => "unknown",
+        }
+    }
+
+    fn from_label(label: &str) -> Self {
+        match label {
+            "ready" => ProbeReason::Ready,
+            "imports_missing" => ProbeReason::ImportsMissing,
+            "repo_missing" => ProbeReason::RepoMissing,
+            "weights_missing" => ProbeReason::WeightsMissing,
+            _ => ProbeReason::Unknown,
+        }
+    }
+}
+
+/// Typed view of `GET /probe`. Every probe field is optional; mock mode only sends `ok`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SidecarStatus {
+    pub ok: bool,
+    pub ready: bool,
+    pub reason: ProbeReason,
+    pub mode: Option<String>,
+    pub model: Option<String>,
+    pub preflight_ok: Option<bool>,
+    pub missing_imports: Vec<String>,
+    pub optional_missing: Vec<String>,
+    pub missing_files: Vec<String>,
+    pub missing_weights: Vec<String>,
+}
+
+impl SidecarStatus {
+    pub fn from_probe(probe: &Value) -> Self {
+        let ok = probe.get("ok").and_then(|v| v.as_bool()).unwrap_or(false);
+        let ready = probe.get("ready").and_then(|v| v.as_bool()).unwrap_or(ok);
+        let reason = match probe.get("reason").and_then(|v| v.as_str()) {
+            Some(label) => ProbeReason::from_label(label),
+            None if ready => ProbeReason::Ready,
+            None => ProbeReason::Unknown,
+        };
+        Self {
+            ok,
+            ready,
+            reason,
+            mode: string_field(probe, "mode"),
+            model: string_field(probe, "model"),
+            preflight_ok: probe.get("preflight_ok").and_then(|v| v.as_bool()),
+            missing_imports: string_list(probe, "missing_imports"),
+            optional_missing: string_list(probe, "optional_missing"),
+            missing_files: string_list(probe, "missing_files"),
+            missing_weights: string_list(probe, "missing_weights"),
+        }
+    }
+}
+
+fn string_field(value: &Value, key: &str) -> Option<String> {
+    value.get(key).and_then(|v| v.as_str()).map(str::to_string)
+}
+
+fn string_list(value: &Value, key: &str) -> Vec<String> {
+    value
+        .get(key)
+        .and_then(|v| v.as_array())
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|item| item.as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn mock_probe_only_has_ok() {
+        let status = SidecarStatus::from_probe(&json!({
+            "ok": true,
+            "model": "omniparser-mock",
+            "gpu": "unknown",
+            "message": "Omniparser API ready (mock)"
+        }));
+        assert!(status.ok && status.ready);
+        assert_eq!(status.reason, ProbeReason::Ready);
+        assert_eq!(status.model.as_deref(), Some("omniparser-mock"));
+        assert_eq!(status.mode, None);
+        assert_eq!(status.preflight_ok, None);
+        assert!(status.missing_weights.is_empty());
+    }
+
+    #[test]
+    fn real_probe_reports_what_is_missing() {
+        let status = SidecarStatus::from_probe(&json!({
+            "ok": false,
+            "mode": "real_local_untitled",
+            "preflight_ok": true,
+            "ready": false,
+            "reason": "weights_missing",
+            "missing_imports": [],
+            "optional_missing": ["paddleocr"],
+            "missing_files": [],
+            "missing_weights": ["icon_detect/model.pt", 7, null],
+            "required_weights": { "icon_detect": "C:/w/icon_detect/model.pt" },
+            "versions": { "torch": "2.3.0" }
+        }));
+        assert!(!status.ok && !status.ready);
+        assert_eq!(status.reason, ProbeReason::WeightsMissing);
+        assert_eq!(status.reason.as_str(), "weights_missing");
+        assert_eq!(status.mode.as_deref(), Some("real_local_untitled"));
+        assert_eq!(status.preflight_ok, Some(true));
+        assert_eq!(status.optional_missing, ["paddleocr"]);
+        // Non-string entries are skipped rather than failing the probe.
+        assert_eq!(status.missing_weights, ["icon_detect/model.pt"]);
+    }
+
+    #[test]
+    fn unknown_or_missing_reason() {
+        let cases = [
+            (json!({ "ok": false }), false, ProbeReason::Unknown),
+            (
+                json!({ "ok": true, "ready": false }),
+                false,
+                ProbeReason::Unknown,
+            ),
+            (json!({ "ready": true }), true, ProbeReason::Ready),
+            (
+                json!({ "ok": false, "reason": "gpu_on_fire" }),
+                false,
+                ProbeReason::Unknown,
+            ),
+            (
+                json!({ "ok": false, "reason": "repo_missing" }),
+                false,
+                ProbeReason::RepoMissing,
+            ),
+            (
+                json!({ "ok": false, "reason": "imports_missing" }),
+                false,
+                ProbeReason::ImportsMissing,
+            ),
+            (json!(null), false, ProbeReason::Unknown),
+            (json!("ok"), false, ProbeReason::Unknown),
+        ];
+        for (probe, ready, reason) in cases {
+            let status = SidecarStatus::from_probe(&probe);
+            assert_eq!((status.ready, status.reason), (ready, reason), "{probe}");
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use serde::{Deserialize, Serialize};
+
+/// What to do with sensitive content.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    /// Replace with a short salted SHA-256 digest, so equal values stay comparable.
+    Hash,
+    /// Replace with a `[redacted:<name>]` placeholder.
+    Mask,
+    /// Remove the whole field.
+    Drop,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PatternRule {
+    pub name: String,
+    pub regex: String,
+    #[serde(default = "default_action")]
+    pub action: Action,
+}
+
+/// Field-level overrides for specific applications (matched case-insensitively).
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppRule {
+    pub apps: Vec<String>,
+    /// Applied to the whole window title (and AW `title`/`url` event data).
+    pub title: Option<Action>,
+    /// Applied to every parsed screen text captured while the app is focused.
+    pub text: Option<Action>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RedactionConfig {
+    pub enabled: bool,
+    /// Builtin deny-lists; each entry is `email`, `token` or `card`.
+    pub builtin: Vec<String>,
+    /// Action for builtin matches.
+    pub builtin_action: Action,
+    pub patterns: Vec<PatternRule>,
+    pub apps: Vec<AppRule>,
+    /// Mixed into every hash; set a private value so hashes cannot be brute-forced.
+    pub hash_salt: String,
+    pub screenshots: ScreenshotConfig,
+}
+
+impl Default for RedactionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            builtin: vec!["email".to_string(), "token".to_string(), "card".to_string()],
+            builtin_action: Action::Mask,
+            patterns: Vec::new(),
+            apps: Vec::new(),
+            hash_salt: String::new(),
+            screenshots: ScreenshotConfig::default(),
+        }
+    }
+}
+
+/// How sensitive regions are painted over in saved screenshots.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PixelMode {
+    /// Keep screenshots as captured.
+    Off,
+    /// Fill each region with solid black.
+    #[default]
+    Blackout,
+    /// Heavy Gaussian blur; cheaper to look at, but weaker than blackout.
+    Blur,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScreenshotConfig {
+    pub mode: PixelMode,
+    /// Never write the unredacted capture to disk: the image is parsed in
+    /// memory and only the redacted variant is saved. Implies `blackout`
+    /// when `mode` is `off`.
+    pub strict: bool,
+    /// Lets `resources/read` serve the original capture when a client passes
+    /// `unredacted: true`. Off by default: clients only see redacted images.
+    pub allow_unredacted: bool,
+    /// Grown around every sensitive box so anti-aliased edges are covered too.
+    pub padding_px: u32,
+    /// Labels (case-insensitive substrings) whose neighbouring input field is
+    /// treated as a password field.
+    pub password_labels: Vec<String>,
+}
+
+impl Default for ScreenshotConfig {
+    fn default() -> Self {
+        Self {
+            mode: PixelMode::Blackout,
+            strict: false,
+            allow_unredacted: false,
+            padding_px: 4,
+            password_labels: vec![
+                "password".to_string(),
+                "passcode".to_string(),
+                "passwort".to_string(),
+                "mot de passe".to_string(),
+            ],
+        }
+    }
+}
+
+impl ScreenshotConfig {
+    /// The mode actually applied, with `strict` forcing a non-`off` mode.
+    pub fn effective_mode(&self) -> PixelMode {
+        match (self.mode, self.strict) {
+            (PixelMode::Off, true) => PixelMode::Blackout,
+            (mode, _) => mode,
+        }
+    }
+}
+
+fn default_action() -> Action {
+    Action::Mask
+}
@@ -0,0 +1,79 @@
+use omni_client::{ParsedElement, PixelBox};
+
+use crate::{Field, Redactor};
+
+/// Characters password inputs render instead of the typed text.
+const MASK_CHARS: &[char] = &['•', '●', '*', '·', '∙', '⁕', '○'];
+
+impl Redactor {
+    /// Pixel regions of `elements` that must not survive in a saved screenshot.
+    ///
+    /// Call this with the elements as parsed, before [`Redactor::elements`]
+    /// rewrites their text. An element is sensitive when its text hits a
+    /// deny-list pattern, when `app` has a per-app text rule, or when it looks
+    /// like a password field: a run of mask characters, or the input next to a
+    /// password label.
+    pub fn sensitive_regions(
+        &self,
+        elements: &[ParsedElement],
+        app: Option<&str>,
+    ) -> Vec<PixelBox> {
+        if !self.enabled {
+            return Vec::new();
+        }
+        let whole_app = self.app_action(app, Field::Text).is_some();
+        let labels: Vec<PixelBox> = elements
+            .iter()
+            .filter(|e| e.text().is_some_and(|t| self.is_password_label(t)))
+            .filter_map(|e| e.bbox_px)
+            .collect();
+
+        let mut regions = Vec::new();
+        for element in elements {
+            let Some(bbox) = element.bbox_px else {
+                continue;
+            };
+            let sensitive = whole_app
+                || element.text().is_some_and(|text| {
+                    is_masked(text) || self.text(text, None, Field::Text).as_deref() != Some(text)
+                })
+                || (element.interactivity == Some(true)
+                    && labels.iter().any(|label| is_field_for(label, &bbox)));
+            if sensitive {
+                regions.push(bbox);
+            }
+        }
+        regions
+    }
+
+    fn is_password_label(&self, text: &str) -> bool {
+        let lower = text.to_lowercase();
+        self.screenshots
+            .password_labels
+            .iter()
+            .any(|label| lower.contains(&label.to_lowercase()))
+    }
+}
+
+fn is_masked(text: &str) -> bool {
+    let masked = text.chars().filter(|c| MASK_CHARS.contains(c)).count();
+    masked >= 4 && masked * 2 >= text.chars().filter(|c| !c.is_whitespace()).count()
+}
+
+/// Whether `field` sits where the input for `label` usually is: on the same
+/// row to the right, or just below.
+fn is_field_for(label: &PixelBox, field: &PixelBox) -> bool {
+    if label == field {
+        return false;
+    }
+    // Sidecar boxes are not trusted to stay small; u64 keeps the sums exact.
+    let [lx, ly, lw, lh] = [label.x, label.y, label.width, label.height].map(u64::from);
+    let [fx, fy, fw, fh] = [field.x, field.y, field.width, field.height].map(u64::from);
+    let row = lh.max(1);
+    let label_mid = ly + lh / 2;
+    let same_row =
+        fy <= label_mid && label_mid <= fy + fh && fx >= lx + lw / 2 && fx <= lx + lw + row * 8;
+    let below =
+        fy >= ly + lh / 2 && fy <= ly + lh + row * 2 && fx + fw >= lx && fx <= lx + lw + row * 4;
+    same_row || below
+}
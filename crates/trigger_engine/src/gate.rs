@@ -0,0 +1,372 @@
+use std::collections::{HashMap, VecDeque};
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{ActivitySignal, Evaluation, TriggerInput};
+
+/// Turns per-poll scores into fire decisions.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GateConfig {
+    /// Fire when the score reaches this value.
+    pub fire_above: f64,
+    /// After firing, stay disarmed until the score drops to this value ...
+    pub rearm_below: f64,
+    /// ... and stays there this long; change-driven rules score 0 on the very
+    /// next poll, so without a hold the gate would re-arm at once.
+    pub rearm_after_secs: u64,
+    /// A candidate fires only after no newer candidate arrived for this long.
+    pub debounce_secs: u64,
+    /// Minimum time between two fires; candidates inside the window are deferred.
+    pub min_interval_secs: u64,
+    /// Maximum fires in any rolling hour; 0 disables the budget.
+    pub max_per_hour: u32,
+}
+
+impl Default for GateConfig {
+    fn default() -> Self {
+        Self {
+            fire_above: 0.7,
+            rearm_below: 0.3,
+            rearm_after_secs: 30,
+            debounce_secs: 0,
+            min_interval_secs: 0,
+            max_per_hour: 0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Suppression {
+    /// Already fired; waiting for the score to stay at or below `rearm_below`
+    /// for `rearm_after_secs`.
+    Disarmed {
+        rearm_below: f64,
+    },
+    Debounce {
+        remaining_secs: f64,
+    },
+    MinInterval {
+        remaining_secs: f64,
+    },
+    HourlyBudget {
+        limit: u32,
+    },
+    BelowThreshold {
+        fire_above: f64,
+    },
+    /// The rule matched but fired less than its `cooldown_secs` ago.
+    Cooldown {
+        rule: String,
+        remaining_secs: f64,
+    },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PendingFire {
+    pub since: DateTime<Utc>,
+    pub evaluation: Evaluation,
+    /// Observation before the candidate; a deferred release measures changes
+    /// from here to the signal current at release time.
+    #[serde(default)]
+    pub previous: Option<ActivitySignal>,
+}
+
+/// Everything the engine remembers between polls, persisted so a restart resumes where it left off.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TriggerState {
+    pub armed: bool,
+    /// While disarmed, since when the score has stayed at or below `rearm_below`.
+    pub below_since: Option<DateTime<Utc>>,
+    pub pending: Option<PendingFire>,
+    pub last_fire: Option<DateTime<Utc>>,
+    /// Fire times within the last hour, oldest first.
+    pub recent_fires: VecDeque<DateTime<Utc>>,
+    /// Last time each rule contributed, for per-rule cooldowns.
+    pub rule_last_matched: HashMap<String, DateTime<Utc>>,
+    /// Last observed activity, so a resumed watcher does not see a fresh start.
+    pub last_signal: Option<ActivitySignal>,
+}
+
+impl Default for TriggerState {
+    fn default() -> Self {
+        Self {
+            armed: true,
+            below_since: None,
+            pending: None,
+            last_fire: None,
+            recent_fires: VecDeque::new(),
+            rule_last_matched: HashMap::new(),
+            last_signal: None,
+        }
+    }
+}
+
+impl TriggerState {
+    /// Records a fire at `now`: disarms the gate and charges the rate limits.
+    pub fn record_fire(&mut self, now: DateTime<Utc>) {
+        self.armed = false;
+        self.below_since = None;
+        self.last_fire = Some(now);
+        self.recent_fires.push_back(now);
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GateOutcome {
+    /// A candidate that cleared debounce and rate limits. The caller re-evaluates
+    /// it against the current input and calls [`TriggerState::record_fire`] if it
+    /// still fires.
+    pub released: Option<PendingFire>,
+    pub suppressed: Vec<Suppression>,
+}
+
+impl GateConfig {
+    /// Whether `evaluation` is strong enough to fire.
+    pub fn is_candidate(&self, evaluation: &Evaluation) -> bool {
+        evaluation.score >= self.fire_above && !evaluation.contributions.is_empty()
+    }
+
+    pub fn step(
+        &self,
+        state: &mut TriggerState,
+        input: &TriggerInput,
+        evaluation: &Evaluation,
+    ) -> GateOutcome {
+        let now = input.now;
+        let mut suppressed = Vec::new();
+        let hour_ago = now - Duration::hours(1);
+        while state.recent_fires.front().is_some_and(|t| *t <= hour_ago) {
+            state.recent_fires.pop_front();
+        }
+
+        if state.armed || evaluation.score > self.rearm_below {
+            state.below_since = None;
+        } else {
+            let since = *state.below_since.get_or_insert(now);
+            if remaining(since, self.rearm_after_secs, now) <= 0.0 {
+                state.armed = true;
+                state.below_since = None;
+            }
+        }
+        if self.is_candidate(evaluation) {
+            if state.armed {
+                state.pending = Some(PendingFire {
+                    since: now,
+                    evaluation: evaluation.clone(),
+                    previous: input.previous.clone(),
+                });
+            } else {
+                suppressed.push(Suppression::Disarmed {
+                    rearm_below: self.rearm_below,
+                });
+            }
+        }
+
+        let Some(pending) = state.pending.as_ref() else {
+            return GateOutcome {
+                released: None,
+                suppressed,
+            };
+        };
+
+        let debounce_left = remaining(pending.since, self.debounce_secs, now);
+        if debounce_left > 0.0 {
+            suppressed.push(Suppression::Debounce {
+                remaining_secs: debounce_left,
+            });
+            return GateOutcome {
+                released: None,
+                suppressed,
+            };
+        }
+        if let Some(last) = state.last_fire {
+            let interval_left = remaining(last, self.min_interval_secs, now);
+            if interval_left > 0.0 {
+                suppressed.push(Suppression::MinInterval {
+                    remaining_secs: interval_left,
+                });
+                return GateOutcome {
+                    released: None,
+                    suppressed,
+                };
+            }
+        }
+        if self.max_per_hour > 0 && state.recent_fires.len() >= self.max_per_hour as usize {
+            // Dropped rather than deferred: the budget may not free up for up to an hour.
+            state.pending = None;
+            suppressed.push(Suppression::HourlyBudget {
+                limit: self.max_per_hour,
+            });
+            return GateOutcome {
+                released: None,
+                suppressed,
+            };
+        }
+
+        GateOutcome {
+            released: state.pending.take(),
+            suppressed,
+        }
+    }
+}
+
+fn remaining(since: DateTime<Utc>, window_secs: u64, now: DateTime<Utc>) -> f64 {
+    let elapsed = (now - since).num_milliseconds() as f64 / 1000.0;
+    (window_secs as f64 - elapsed).max(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RuleContribution;
+
+    fn at(secs: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(1_700_000_000 + secs, 0).unwrap()
+    }
+
+    fn input(secs: i64) -> TriggerInput {
+        TriggerInput {
+            now: at(secs),
+            reason: None,
+            current: ActivitySignal::default(),
+            previous: None,
+            dwell_secs: 0.0,
+        }
+    }
+
+    fn scored(score: f64) -> Evaluation {
+        let contributions = if score > 0.0 {
+            vec![RuleContribution {
+                rule: "test".to_string(),
+                weight: score,
+                explanation: "test".to_string(),
+            }]
+        } else {
+            Vec::new()
+        };
+        Evaluation {
+            score,
+            contributions,
+            cooling_down: Vec::new(),
+        }
+    }
+
+    /// Steps the gate and, like the engine, records a fire for every release.
+    fn poll(gate: &GateConfig, state: &mut TriggerState, secs: i64, score: f64) -> GateOutcome {
+        let outcome = gate.step(state, &input(secs), &scored(score));
+        if outcome.released.is_some() {
+            state.record_fire(at(secs));
+        }
+        outcome
+    }
+
+    fn gate() -> GateConfig {
+        GateConfig {
+            rearm_after_secs: 0,
+            ..GateConfig::default()
+        }
+    }
+
+    #[test]
+    fn debounce_holds_the_candidate_until_activity_settles() {
+        let gate = GateConfig {
+            debounce_secs: 3,
+            ..gate()
+        };
+        let mut state = TriggerState::default();
+
+        let first = poll(&gate, &mut state, 0, 0.8);
+        assert!(first.released.is_none());
+        assert!(matches!(
+            first.suppressed[..],
+            [Suppression::Debounce { remaining_secs }] if remaining_secs == 3.0
+        ));
+        // A newer candidate restarts the window.
+        assert!(poll(&gate, &mut state, 2, 0.9).released.is_none());
+        assert!(poll(&gate, &mut state, 4, 0.0).released.is_none());
+
+        let released = poll(&gate, &mut state, 5, 0.0).released.unwrap();
+        assert_eq!(released.since, at(2));
+        assert_eq!(released.evaluation.score, 0.9);
+        assert!(state.pending.is_none());
+    }
+
+    #[test]
+    fn rearms_only_after_the_score_stays_low() {
+        let gate = GateConfig {
+            rearm_after_secs: 30,
+            ..gate()
+        };
+        let mut state = TriggerState::default();
+        assert!(poll(&gate, &mut state, 0, 0.8).released.is_some());
+        assert!(!state.armed);
+
+        // The next poll of a change-driven rule scores 0; that alone must not re-arm.
+        assert!(poll(&gate, &mut state, 5, 0.0).released.is_none());
+        assert!(!state.armed);
+        let blocked = poll(&gate, &mut state, 10, 0.8);
+        assert!(blocked.released.is_none());
+        assert!(matches!(
+            blocked.suppressed[..],
+            [Suppression::Disarmed { .. }]
+        ));
+
+        // The candidate above interrupted the low stretch, so the hold starts over.
+        poll(&gate, &mut state, 15, 0.0);
+        poll(&gate, &mut state, 40, 0.0);
+        assert!(!state.armed);
+        poll(&gate, &mut state, 45, 0.2);
+        assert!(state.armed);
+        assert!(poll(&gate, &mut state, 46, 0.8).released.is_some());
+    }
+
+    #[test]
+    fn min_interval_defers_rather_than_drops() {
+        let gate = GateConfig {
+            min_interval_secs: 30,
+            ..gate()
+        };
+        let mut state = TriggerState::default();
+        assert!(poll(&gate, &mut state, 0, 0.8).released.is_some());
+        poll(&gate, &mut state, 5, 0.0);
+
+        let deferred = poll(&gate, &mut state, 10, 0.8);
+        assert!(deferred.released.is_none());
+        assert!(matches!(
+            deferred.suppressed[..],
+            [Suppression::MinInterval { remaining_secs }] if remaining_secs == 20.0
+        ));
+        assert!(state.pending.is_some());
+
+        let released = poll(&gate, &mut state, 30, 0.0).released.unwrap();
+        assert_eq!(released.since, at(10));
+    }
+
+    #[test]
+    fn hourly_budget_drops_candidates_until_fires_age_out() {
+        let gate = GateConfig {
+            max_per_hour: 2,
+            ..gate()
+        };
+        let mut state = TriggerState::default();
+        assert!(poll(&gate, &mut state, 0, 0.8).released.is_some());
+        poll(&gate, &mut state, 1, 0.0);
+        assert!(poll(&gate, &mut state, 2, 0.8).released.is_some());
+        poll(&gate, &mut state, 3, 0.0);
+
+        let over = poll(&gate, &mut state, 4, 0.8);
+        assert!(over.released.is_none());
+        assert!(matches!(
+            over.suppressed[..],
+            [Suppression::HourlyBudget { limit: 2 }]
+        ));
+        assert!(state.pending.is_none());
+
+        // The first fire has left the rolling hour.
+        assert!(poll(&gate, &mut state, 3600, 0.8).released.is_some());
+    }
+}
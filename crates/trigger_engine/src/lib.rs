@@ -1,15 +1,347 @@
+mod gate;
+mod rules;
+
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+pub use gate::{GateConfig, GateOutcome, PendingFire, Suppression, TriggerState};
+pub use rules::{AfkTransition, Rule, RuleConfig, RuleError, RuleWhen, RulesFile, TimeOfDay};
+
+/// Snapshot of the foreground activity as seen by the trigger engine.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ActivitySignal {
+    pub app: Option<String>,
+    pub title: Option<String>,
+    pub afk: Option<bool>,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct TriggerInput {
-    pub reason: String,
-    pub hint: Option<f32>,
+    pub now: DateTime<Utc>,
+    /// Explicit reason supplied by the caller (e.g. a manual `build-nowframe`).
+    pub reason: Option<String>,
+    pub current: ActivitySignal,
+    /// Previous observation; `None` on the first poll.
+    pub previous: Option<ActivitySignal>,
+    /// Seconds the current window has been in the foreground.
+    pub dwell_secs: f64,
+}
+
+impl TriggerInput {
+    pub fn manual(reason: &str, current: ActivitySignal) -> Self {
+        Self {
+            now: Utc::now(),
+            reason: Some(reason.to_string()),
+            current,
+            previous: None,
+            dwell_secs: 0.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RuleContribution {
+    pub rule: String,
+    pub weight: f64,
+    pub explanation: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Evaluation {
+    pub score: f64,
+    pub contributions: Vec<RuleContribution>,
+    /// Rules that matched but were skipped because their cooldown had not elapsed.
+    pub cooling_down: Vec<Suppression>,
+}
+
+/// Explains why a trigger did or did not fire; embedded on the NowFrame it produced.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Decision {
+    pub fired: bool,
+    pub decided_at: DateTime<Utc>,
+    /// Score of the evaluation that fired, or of the current poll otherwise.
+    pub score: f64,
+    pub threshold: f64,
+    pub contributions: Vec<RuleContribution>,
+    pub suppressed_by: Vec<Suppression>,
+}
+
+impl Decision {
+    /// Names of the contributing rules joined with `+`, used as the NowFrame reason.
+    pub fn reason(&self) -> String {
+        self.contributions
+            .iter()
+            .map(|c| c.rule.as_str())
+            .collect::<Vec<_>>()
+            .join("+")
+    }
+}
+
+pub struct TriggerEngine {
+    rules: Vec<Rule>,
+    gate: GateConfig,
+    state: TriggerState,
+}
+
+impl TriggerEngine {
+    pub fn new(file: RulesFile) -> Result<Self, RuleError> {
+        let rules = file
+            .rules
+            .into_iter()
+            .map(Rule::compile)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self {
+            rules,
+            gate: GateConfig::default(),
+            state: TriggerState::default(),
+        })
+    }
+
+    pub fn builtin() -> Self {
+        Self::new(RulesFile::builtin()).expect("builtin rules compile")
+    }
+
+    /// Loads rules from `path`, or the builtin set when no path is given.
+    pub fn from_path(path: Option<&Path>) -> Result<Self, RuleError> {
+        match path {
+            Some(path) => Self::new(RulesFile::load(path)?),
+            None => Ok(Self::builtin()),
+        }
+    }
+
+    pub fn with_gate(mut self, gate: GateConfig) -> Self {
+        self.gate = gate;
+        self
+    }
+
+    /// Resumes from a previously persisted state.
+    pub fn with_state(mut self, state: TriggerState) -> Self {
+        self.state = state;
+        self
+    }
+
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    pub fn gate(&self) -> &GateConfig {
+        &self.gate
+    }
+
+    pub fn state(&self) -> &TriggerState {
+        &self.state
+    }
+
+    pub fn state_mut(&mut self) -> &mut TriggerState {
+        &mut self.state
+    }
+
+    /// Evaluates the rules and passes the score through debounce, hysteresis and rate limits.
+    pub fn step(&mut self, input: &TriggerInput) -> Decision {
+        let mut evaluation = self.evaluate(input);
+        let outcome = self.gate.step(&mut self.state, input, &evaluation);
+        let gated = outcome.suppressed;
+        if let Some(pending) = outcome.released {
+            // A deferred candidate is re-checked against what is current now, with
+            // changes measured from its own baseline; one admitted on this poll
+            // already was.
+            let released = if pending.since == input.now {
+                pending.evaluation
+            } else {
+                let replay = TriggerInput {
+                    previous: pending.previous,
+                    ..input.clone()
+                };
+                self.evaluate_candidate(&replay, Some(pending.since))
+            };
+            if self.gate.is_candidate(&released) {
+                self.state.record_fire(input.now);
+                return Decision {
+                    fired: true,
+                    decided_at: input.now,
+                    score: released.score,
+                    threshold: self.gate.fire_above,
+                    contributions: released.contributions,
+                    suppressed_by: released.cooling_down,
+                };
+            }
+            evaluation = released;
+        }
+        // Score, contributions and suppressions all describe the same evaluation.
+        let mut suppressed_by = [evaluation.cooling_down, gated].concat();
+        if evaluation.score < self.gate.fire_above {
+            suppressed_by.push(Suppression::BelowThreshold {
+                fire_above: self.gate.fire_above,
+            });
+        }
+        Decision {
+            fired: false,
+            decided_at: input.now,
+            score: evaluation.score,
+            threshold: self.gate.fire_above,
+            contributions: evaluation.contributions,
+            suppressed_by,
+        }
+    }
+
+    /// Scores an ad-hoc input against the rules only; gate state is left untouched.
+    /// Rule cooldowns still apply, so resume from the watcher's state first to see
+    /// what it would make of the same input.
+    pub fn explain(&mut self, input: &TriggerInput) -> Decision {
+        let evaluation = self.evaluate(input);
+        let mut suppressed_by = evaluation.cooling_down;
+        let fired = evaluation.score >= self.gate.fire_above;
+        if !fired {
+            suppressed_by.push(Suppression::BelowThreshold {
+                fire_above: self.gate.fire_above,
+            });
+        }
+        Decision {
+            fired,
+            decided_at: input.now,
+            score: evaluation.score,
+            threshold: self.gate.fire_above,
+            contributions: evaluation.contributions,
+            suppressed_by,
+        }
+    }
+
+    /// Sums the weights of all matching rules that are out of cooldown; the score is clamped to 0..=1.
+    pub fn evaluate(&mut self, input: &TriggerInput) -> Evaluation {
+        self.evaluate_candidate(input, None)
+    }
+
+    /// Like [`evaluate`](Self::evaluate), but matches recorded at or after
+    /// `candidate_since` belong to the candidate being re-checked and do not
+    /// start a cooldown against it.
+    fn evaluate_candidate(
+        &mut self,
+        input: &TriggerInput,
+        candidate_since: Option<DateTime<Utc>>,
+    ) -> Evaluation {
+        let mut contributions = Vec::new();
+        let mut cooling_down = Vec::new();
+
+        for rule in &self.rules {
+            let Some(why) = rule.matches(input) else {
+                continue;
+            };
+            let cooldown = rule.config.cooldown_secs as f64;
+            let last = self
+                .state
+                .rule_last_matched
+                .get(rule.name())
+                .filter(|last| candidate_since.is_none_or(|since| **last < since));
+            if let Some(last) = last {
+                let elapsed = (input.now - *last).num_milliseconds() as f64 / 1000.0;
+                if cooldown > 0.0 && elapsed < cooldown {
+                    cooling_down.push(Suppression::Cooldown {
+                        rule: rule.name().to_string(),
+                        remaining_secs: cooldown - elapsed,
+                    });
+                    continue;
+                }
+            }
+            self.state
+                .rule_last_matched
+                .insert(rule.name().to_string(), input.now);
+            let explanation = if why.is_empty() {
+                "always".to_string()
+            } else {
+                why.join(", ")
+            };
+            contributions.push(RuleContribution {
+                rule: rule.name().to_string(),
+                weight: rule.config.weight,
+                explanation,
+            });
+        }
+
+        let score = contributions
+            .iter()
+            .map(|c| c.weight)
+            .sum::<f64>()
+            .clamp(0.0, 1.0);
+        Evaluation {
+            score,
+            contributions,
+            cooling_down,
+        }
+    }
 }
 
-pub fn score(input: &TriggerInput) -> f32 {
-    let mut base = input.hint.unwrap_or(0.5);
-    if !input.reason.trim().is_empty() {
-        base += 0.2;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(secs: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(1_700_000_000 + secs, 0).unwrap()
+    }
+
+    fn signal(app: &str) -> ActivitySignal {
+        ActivitySignal {
+            app: Some(app.to_string()),
+            title: None,
+            afk: Some(false),
+        }
+    }
+
+    fn engine() -> TriggerEngine {
+        let rules = RulesFile::from_toml_str(
+            r#"
+            [[rules]]
+            name = "app_switch"
+            weight = 0.8
+            cooldown_secs = 10
+            [rules.when]
+            app_changed = true
+            "#,
+        )
+        .unwrap();
+        TriggerEngine::new(rules).unwrap().with_gate(GateConfig {
+            debounce_secs: 3,
+            ..GateConfig::default()
+        })
+    }
+
+    fn poll(engine: &mut TriggerEngine, secs: i64, previous: &str, current: &str) -> Decision {
+        engine.step(&TriggerInput {
+            now: at(secs),
+            reason: None,
+            current: signal(current),
+            previous: Some(signal(previous)),
+            dwell_secs: 0.0,
+        })
+    }
+
+    #[test]
+    fn deferred_switch_fires_from_the_current_input() {
+        let mut engine = engine();
+        assert!(!poll(&mut engine, 0, "a", "b").fired);
+        assert!(!poll(&mut engine, 2, "b", "b").fired);
+
+        let decision = poll(&mut engine, 3, "b", "b");
+        assert!(decision.fired);
+        assert_eq!(decision.reason(), "app_switch");
+        assert_eq!(decision.contributions[0].explanation, "app changed a -> b");
+        // Its own match does not put the candidate into cooldown.
+        assert!(decision.suppressed_by.is_empty());
+    }
+
+    #[test]
+    fn deferred_switch_that_was_undone_does_not_fire() {
+        let mut engine = engine();
+        assert!(!poll(&mut engine, 0, "a", "b").fired);
+        // Cooling down, so this switch back is no new candidate.
+        assert!(!poll(&mut engine, 1, "b", "a").fired);
+
+        let decision = poll(&mut engine, 3, "a", "a");
+        assert!(!decision.fired);
+        assert_eq!(decision.score, 0.0);
+        assert!(decision.contributions.is_empty());
+        assert!(engine.state().armed);
+        assert!(engine.state().last_fire.is_none());
     }
-    base.clamp(0.0, 1.0)
 }
@@ -0,0 +1,546 @@
+use std::fs;
+use std::path::Path;
+
+use chrono::{Local, NaiveTime};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::TriggerInput;
+
+#[derive(Debug, Error)]
+pub enum RuleError {
+    #[error("read rules failed: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("parse rules failed: {0}")]
+    Toml(#[from] toml::de::Error),
+    #[error("rule {rule}: invalid title_regex: {source}")]
+    Regex {
+        rule: String,
+        #[source]
+        source: regex::Error,
+    },
+    #[error("rule {rule}: invalid time {value:?} (expected HH:MM)")]
+    Time { rule: String, value: String },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AfkTransition {
+    ToAfk,
+    ToNotAfk,
+    Any,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct TimeOfDay {
+    pub start: String,
+    pub end: String,
+}
+
+/// Conditions of a rule; every condition that is set must hold for the rule to match.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RuleWhen {
+    pub reason_present: Option<bool>,
+    pub first_observation: Option<bool>,
+    pub app: Vec<String>,
+    pub app_changed: Option<bool>,
+    pub title_regex: Option<String>,
+    pub title_changed: Option<bool>,
+    pub afk: Option<bool>,
+    pub afk_transition: Option<AfkTransition>,
+    pub time_of_day: Option<TimeOfDay>,
+    pub min_dwell_secs: Option<f64>,
+    pub max_dwell_secs: Option<f64>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RuleConfig {
+    pub name: String,
+    pub weight: f64,
+    #[serde(default)]
+    pub cooldown_secs: u64,
+    #[serde(default)]
+    pub when: RuleWhen,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct RulesFile {
+    #[serde(default)]
+    pub rules: Vec<RuleConfig>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Rule {
+    pub config: RuleConfig,
+    title_regex: Option<Regex>,
+    time_window: Option<(NaiveTime, NaiveTime)>,
+}
+
+impl Rule {
+    pub fn compile(config: RuleConfig) -> Result<Self, RuleError> {
+        let title_regex = match &config.when.title_regex {
+            Some(pattern) => Some(
+                RegexBuilder::new(pattern)
+                    .case_insensitive(true)
+                    .build()
+                    .map_err(|source| RuleError::Regex {
+                        rule: config.name.clone(),
+                        source,
+                    })?,
+            ),
+            None => None,
+        };
+        let time_window = match &config.when.time_of_day {
+            Some(window) => Some((
+                parse_time(&config.name, &window.start)?,
+                parse_time(&config.name, &window.end)?,
+            )),
+            None => None,
+        };
+        Ok(Self {
+            config,
+            title_regex,
+            time_window,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    /// Returns the explanation of every matched condition, or `None` if any condition fails.
+    pub fn matches(&self, input: &TriggerInput) -> Option<Vec<String>> {
+        let when = &self.config.when;
+        let current = &input.current;
+        let previous = input.previous.as_ref();
+        let mut why = Vec::new();
+
+        if let Some(expected) = when.reason_present {
+            let present = input
+                .reason
+                .as_deref()
+                .is_some_and(|r| !r.trim().is_empty());
+            if present != expected {
+                return None;
+            }
+            if present {
+                why.push(format!(
+                    "reason {:?}",
+                    input.reason.as_deref().unwrap_or("")
+                ));
+            }
+        }
+
+        if let Some(expected) = when.first_observation {
+            if previous.is_none() != expected {
+                return None;
+            }
+            if expected {
+                why.push("first observation".to_string());
+            }
+        }
+
+        if !when.app.is_empty() {
+            let app = current.app.as_deref()?;
+            if !when.app.iter().any(|a| a.eq_ignore_ascii_case(app)) {
+                return None;
+            }
+            why.push(format!("app is {}", app));
+        }
+
+        if let Some(expected) = when.app_changed {
+            let changed = previous.is_some_and(|p| p.app != current.app);
+            if changed != expected {
+                return None;
+            }
+            if changed {
+                why.push(format!(
+                    "app changed {} -> {}",
+                    previous.and_then(|p| p.app.as_deref()).unwrap_or("?"),
+                    current.app.as_deref().unwrap_or("?")
+                ));
+            }
+        }
+
+        if let Some(regex) = &self.title_regex {
+            let title = current.title.as_deref()?;
+            if !regex.is_match(title) {
+                return None;
+            }
+            why.push(format!("title matches /{}/", regex.as_str()));
+        }
+
+        if let Some(expected) = when.title_changed {
+            let changed = previous.is_some_and(|p| p.title != current.title);
+            if changed != expected {
+                return None;
+            }
+            if changed {
+                why.push("title changed".to_string());
+            }
+        }
+
+        if let Some(expected) = when.afk {
+            if current.afk != Some(expected) {
+                return None;
+            }
+            why.push(if expected { "afk" } else { "not afk" }.to_string());
+        }
+
+        if let Some(transition) = when.afk_transition {
+            let before = previous.and_then(|p| p.afk)?;
+            let after = current.afk?;
+            let ok = match transition {
+                AfkTransition::ToAfk => !before && after,
+                AfkTransition::ToNotAfk => before && !after,
+                AfkTransition::Any => before != after,
+            };
+            if !ok {
+                return None;
+            }
+            why.push(format!(
+                "afk transition {} -> {}",
+                afk_label(before),
+                afk_label(after)
+            ));
+        }
+
+        if let Some((start, end)) = self.time_window {
+            let local = input.now.with_timezone(&Local).time();
+            let inside = if start <= end {
+                local >= start && local < end
+            } else {
+                local >= start || local < end
+            };
+            if !inside {
+                return None;
+            }
+            why.push(format!(
+                "time {} in {}-{}",
+                local.format("%H:%M"),
+                start.format("%H:%M"),
+                end.format("%H:%M")
+            ));
+        }
+
+        if let Some(min) = when.min_dwell_secs {
+            if input.dwell_secs < min {
+                return None;
+            }
+            why.push(format!("dwell {:.0}s >= {:.0}s", input.dwell_secs, min));
+        }
+
+        if let Some(max) = when.max_dwell_secs {
+            if input.dwell_secs > max {
+                return None;
+            }
+            why.push(format!("dwell {:.0}s <= {:.0}s", input.dwell_secs, max));
+        }
+
+        Some(why)
+    }
+}
+
+impl RulesFile {
+    pub fn from_toml_str(text: &str) -> Result<Self, RuleError> {
+        Ok(toml::from_str(text)?)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, RuleError> {
+        let text = fs::read_to_string(path)?;
+        Self::from_toml_str(&text)
+    }
+
+    /// Rules used when no rules file is configured; mirrors the original change triggers.
+    pub fn builtin() -> Self {
+        let rule = |name: &str, weight: f64, cooldown_secs: u64, when: RuleWhen| RuleConfig {
+            name: name.to_string(),
+            weight,
+            cooldown_secs,
+            when,
+        };
+        Self {
+            rules: vec![
+                rule(
+                    "manual_reason",
+                    0.8,
+                    0,
+                    RuleWhen {
+                        reason_present: Some(true),
+                        ..RuleWhen::default()
+                    },
+                ),
+                rule(
+                    "first_observation",
+                    0.8,
+                    0,
+                    RuleWhen {
+                        first_observation: Some(true),
+                        ..RuleWhen::default()
+                    },
+                ),
+                rule(
+                    "app_switch",
+                    0.8,
+                    10,
+                    RuleWhen {
+                        app_changed: Some(true),
+                        ..RuleWhen::default()
+                    },
+                ),
+                rule(
+                    "afk_transition",
+                    0.7,
+                    0,
+                    RuleWhen {
+                        afk_transition: Some(AfkTransition::Any),
+                        ..RuleWhen::default()
+                    },
+                ),
+                rule(
+                    "title_change",
+                    0.5,
+                    30,
+                    RuleWhen {
+                        title_changed: Some(true),
+                        ..RuleWhen::default()
+                    },
+                ),
+            ],
+        }
+    }
+}
+
+fn parse_time(rule: &str, value: &str) -> Result<NaiveTime, RuleError> {
+    NaiveTime::parse_from_str(value.trim(), "%H:%M").map_err(|_| RuleError::Time {
+        rule: rule.to_string(),
+        value: value.to_string(),
+    })
+}
+
+fn afk_label(afk: bool) -> &'static str {
+    if afk {
+        "afk"
+    } else {
+        "not-afk"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, TimeZone, Utc};
+
+    use super::*;
+    use crate::{ActivitySignal, Suppression, TriggerEngine};
+
+    fn rule(when: &str) -> Rule {
+        let text = format!(
+            "[[rules]]\nname = \"r\"\nweight = 1.0\n[rules.when]\n{}",
+            when
+        );
+        let config = RulesFile::from_toml_str(&text).unwrap().rules.remove(0);
+        Rule::compile(config).unwrap()
+    }
+
+    fn signal(title: Option<&str>, afk: Option<bool>) -> ActivitySignal {
+        ActivitySignal {
+            app: Some("Code.exe".to_string()),
+            title: title.map(str::to_string),
+            afk,
+        }
+    }
+
+    fn input(current: ActivitySignal, previous: Option<ActivitySignal>) -> TriggerInput {
+        TriggerInput {
+            now: DateTime::from_timestamp(1_700_000_000, 0).unwrap(),
+            reason: None,
+            current,
+            previous,
+            dwell_secs: 0.0,
+        }
+    }
+
+    /// An input at `hour:minute` local time, which is what `time_of_day` compares.
+    fn at_local(hour: u32, minute: u32) -> TriggerInput {
+        let now = Local
+            .with_ymd_and_hms(2026, 10, 17, hour, minute, 0)
+            .earliest()
+            .unwrap()
+            .with_timezone(&Utc);
+        TriggerInput {
+            now,
+            ..input(ActivitySignal::default(), None)
+        }
+    }
+
+    #[test]
+    fn overnight_time_window_wraps_midnight() {
+        let night = rule(r#"time_of_day = { start = "22:00", end = "06:00" }"#);
+        for (hour, minute) in [(22, 0), (23, 30), (0, 0), (5, 59)] {
+            assert!(
+                night.matches(&at_local(hour, minute)).is_some(),
+                "{hour}:{minute}"
+            );
+        }
+        for (hour, minute) in [(6, 0), (12, 0), (21, 59)] {
+            assert!(
+                night.matches(&at_local(hour, minute)).is_none(),
+                "{hour}:{minute}"
+            );
+        }
+
+        let day = rule(r#"time_of_day = { start = "09:00", end = "17:00" }"#);
+        assert!(day.matches(&at_local(12, 0)).is_some());
+        assert!(day.matches(&at_local(17, 0)).is_none());
+        assert!(day.matches(&at_local(23, 0)).is_none());
+    }
+
+    #[test]
+    fn afk_transition_directions() {
+        let away =
+            |before, after| input(signal(None, Some(after)), Some(signal(None, Some(before))));
+        let to_afk = rule(r#"afk_transition = "to_afk""#);
+        let to_not_afk = rule(r#"afk_transition = "to_not_afk""#);
+        let any = rule(r#"afk_transition = "any""#);
+
+        assert_eq!(
+            to_afk.matches(&away(false, true)).unwrap(),
+            ["afk transition not-afk -> afk"]
+        );
+        assert!(to_afk.matches(&away(true, false)).is_none());
+        assert!(to_afk.matches(&away(true, true)).is_none());
+
+        assert_eq!(
+            to_not_afk.matches(&away(true, false)).unwrap(),
+            ["afk transition afk -> not-afk"]
+        );
+        assert!(to_not_afk.matches(&away(false, true)).is_none());
+
+        assert!(any.matches(&away(false, true)).is_some());
+        assert!(any.matches(&away(true, false)).is_some());
+        assert!(any.matches(&away(false, false)).is_none());
+
+        // Without a known state on both sides there is no transition.
+        assert!(any
+            .matches(&input(signal(None, Some(true)), None))
+            .is_none());
+        assert!(any
+            .matches(&input(signal(None, Some(true)), Some(signal(None, None))))
+            .is_none());
+    }
+
+    #[test]
+    fn title_regex_is_case_insensitive_and_needs_a_title() {
+        let private = rule(r#"title_regex = "(InPrivate|Incognito)""#);
+        let titled = |title| input(signal(title, None), None);
+
+        assert_eq!(
+            private
+                .matches(&titled(Some("New tab - incognito")))
+                .unwrap(),
+            ["title matches /(InPrivate|Incognito)/"]
+        );
+        assert!(private.matches(&titled(Some("New tab"))).is_none());
+        assert!(private.matches(&titled(None)).is_none());
+    }
+
+    #[test]
+    fn dwell_bounds_are_inclusive() {
+        let dwell = rule("min_dwell_secs = 60.0\nmax_dwell_secs = 300.0");
+        let dwelling = |dwell_secs| TriggerInput {
+            dwell_secs,
+            ..input(signal(None, None), None)
+        };
+
+        assert!(dwell.matches(&dwelling(59.9)).is_none());
+        assert_eq!(
+            dwell.matches(&dwelling(60.0)).unwrap(),
+            ["dwell 60s >= 60s", "dwell 60s <= 300s"]
+        );
+        assert!(dwell.matches(&dwelling(300.0)).is_some());
+        assert!(dwell.matches(&dwelling(300.1)).is_none());
+    }
+
+    #[test]
+    fn cooldown_skips_a_rule_that_matched_recently() {
+        let rules = RulesFile::from_toml_str(
+            r#"
+            [[rules]]
+            name = "slow"
+            weight = 0.5
+            cooldown_secs = 10
+
+            [[rules]]
+            name = "fast"
+            weight = 0.2
+            "#,
+        )
+        .unwrap();
+        let mut engine = TriggerEngine::new(rules).unwrap();
+        let mut at = |secs: i64| {
+            engine.evaluate(&TriggerInput {
+                now: DateTime::from_timestamp(1_700_000_000 + secs, 0).unwrap(),
+                ..input(ActivitySignal::default(), None)
+            })
+        };
+
+        assert_eq!(at(0).score, 0.7);
+
+        let cooling = at(4);
+        assert_eq!(cooling.score, 0.2);
+        assert_eq!(cooling.contributions[0].rule, "fast");
+        assert!(matches!(
+            &cooling.cooling_down[..],
+            [Suppression::Cooldown { rule, remaining_secs }]
+                if rule == "slow" && *remaining_secs == 6.0
+        ));
+
+        // A skipped match does not extend the cooldown.
+        assert_eq!(at(10).score, 0.7);
+    }
+
+    #[test]
+    fn invalid_rules_are_errors() {
+        let compile = |when: &str| {
+            let text = format!(
+                "[[rules]]\nname = \"bad\"\nweight = 1.0\n[rules.when]\n{}",
+                when
+            );
+            Rule::compile(RulesFile::from_toml_str(&text).unwrap().rules.remove(0))
+        };
+        assert!(matches!(
+            compile(r#"title_regex = "(unclosed""#),
+            Err(RuleError::Regex { rule, .. }) if rule == "bad"
+        ));
+        assert!(matches!(
+            compile(r#"time_of_day = { start = "25:00", end = "06:00" }"#),
+            Err(RuleError::Time { value, .. }) if value == "25:00"
+        ));
+
+        for text in [
+            "[[rules]]\nname = \"no_weight\"",
+            "[[rules]\nname = \"broken\"",
+            "[[rules]]\nname = \"r\"\nweight = 1.0\n[rules.when]\nafk_transition = \"sideways\"",
+        ] {
+            assert!(matches!(
+                RulesFile::from_toml_str(text),
+                Err(RuleError::Toml(_))
+            ));
+        }
+
+        let rules = RulesFile::from_toml_str(
+            "[[rules]]\nname = \"bad\"\nweight = 1.0\n[rules.when]\ntitle_regex = \"[\"",
+        )
+        .unwrap();
+        assert!(TriggerEngine::new(rules).is_err());
+    }
+
+    #[test]
+    fn shipped_rules_compile() {
+        let rules =
+            RulesFile::from_toml_str(include_str!("../../../config/trigger_rules.toml")).unwrap();
+        assert!(TriggerEngine::new(rules).is_ok());
+    }
+}
@@ -38,8 +38,20 @@ class Handler(BaseHTTPRequestHandler):
                 "latency": latency_s,
                 "latency_ms": int(latency_s * 1000),
                 "parsed_content_list": [
-                    {"type": "text", "content": "mock text", "score": 0.5},
-                    {"type": "icon", "content": "mock-icon", "score": 0.2},
+                    {
+                        "type": "text",
+                        "content": "mock text",
+                        "bbox_normalized": [0.1, 0.1, 0.3, 0.15],
+                        "interactivity": False,
+                        "score": 0.5,
+                    },
+                    {
+                        "type": "icon",
+                        "content": "mock-icon",
+                        "bbox_normalized": [0.9, 0.02, 0.93, 0.06],
+                        "interactivity": True,
+                        "score": 0.2,
+                    },
                 ],
                 "som_image_base64": "",
             }
@@ -55,6 +67,11 @@ def main():
     parser = argparse.ArgumentParser()
     parser.add_argument("--host", default="127.0.0.1")
     parser.add_argument("--port", type=int, default=8000)
+    # Passed by the daemon's sidecar supervisor, which launches every script the
+    # same way; the mock has a single mode and no model files.
+    parser.add_argument("--mode", default="mock")
+    parser.add_argument("--real-repo", dest="real_repo", default="")
+    parser.add_argument("--weights-root", dest="weights_root", default="")
     args = parser.parse_args()
 
     server = ThreadingHTTPServer((args.host, args.port), Handler)